@@ -1,6 +1,11 @@
 use vampirc_uci::{UciFen, uci::UciMove};
 
-use crate::r#move::Move;
+use crate::{
+    fen::{FenError, STARTPOS},
+    r#move::Move,
+    psqt,
+    zobrist,
+};
 
 pub type Bitboard = u64;
 pub type Square = i16;
@@ -17,6 +22,16 @@ impl AsSquare for Square {
     }
 }
 
+pub fn parse_square(square: &str) -> Option<Square> {
+    let mut chars = square.chars();
+    let file = chars.next()?;
+    let rank = chars.next()?;
+    if chars.next().is_some() || !('a'..='h').contains(&file) || !('1'..='8').contains(&rank) {
+        return None;
+    }
+    Some((file as Square - 'a' as Square) + (rank as Square - '1' as Square) * 8)
+}
+
 pub trait ToSquare {
     fn to_square(&self) -> Square;
 }
@@ -106,6 +121,7 @@ impl Piece {
     }
 }
 
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
 pub enum CastleKind {
     KingSide,
     QueenSide,
@@ -118,6 +134,9 @@ pub struct IrreversibleAspects {
     half_move_clock: u16,
     ep: Square,
     castling_rights: u8,
+    zobrist_hash: u64,
+    // Repetitions are not looked for across a null move
+    null_move: bool,
 }
 
 #[derive(Clone, PartialEq, Eq, Debug)]
@@ -132,46 +151,69 @@ pub struct Board {
     pub queens: Bitboard,
     pub kings: Bitboard,
 
+    // Kept in sync with the bitboards for constant time lookups in get_piece
+    pub mailbox: [Piece; 64],
+
     pub ep: Square,
     pub half_move_clock: u16,
     pub full_move_clock: u16,
 
     // White King | White Queen | Black King | Black Queen
     pub castling_rights: u8,
+    // The rooks the castling rights belong to, in the same order
+    pub castling_rooks: [Square; 4],
     pub turn: Color,
 
+    // Whether castling moves are read and written as the king capturing its own rook
+    pub chess960: bool,
+
     pub game_stack: Vec<IrreversibleAspects>,
 
     pub zobrist_hash: u64,
-    // Pieces (0..768) | Side to move (768..769) | Castling Rights (769..773) | En Passant (773..781)
-    pub zobrist_values: [u64; 781],
+    // Only pawns and kings
+    pub pawn_key: u64,
+    // The number of each piece, regardless of where they are
+    pub material_key: u64,
+
+    // Running evaluation terms, positive for white
+    pub material_score: i64,
+    pub square_table_score: i64,
 }
 
+// Arrays longer than 32 elements don't implement Default
 impl Default for Board {
     fn default() -> Self {
-        let mut zobrist_values = [0; 781];
-
-        for i in zobrist_values.iter_mut() {
-            *i = rand::random();
-        }
-
-        Self {
-            white_pieces: Default::default(),
-            black_pieces: Default::default(),
-            pawns: Default::default(),
-            rooks: Default::default(),
-            knights: Default::default(),
-            bishops: Default::default(),
-            queens: Default::default(),
-            kings: Default::default(),
-            ep: Default::default(),
-            half_move_clock: Default::default(),
-            full_move_clock: Default::default(),
-            castling_rights: Default::default(),
-            turn: Default::default(),
-            game_stack: Default::default(),
-            zobrist_hash: Default::default(),
-            zobrist_values,
+        Board {
+            white_pieces: 0,
+            black_pieces: 0,
+
+            pawns: 0,
+            rooks: 0,
+            knights: 0,
+            bishops: 0,
+            queens: 0,
+            kings: 0,
+
+            mailbox: [Piece::NONE; 64],
+
+            ep: 0,
+            half_move_clock: 0,
+            full_move_clock: 0,
+
+            castling_rights: 0,
+            castling_rooks: [0; 4],
+            turn: Color::None,
+
+            chess960: false,
+
+            game_stack: Vec::new(),
+
+            zobrist_hash: 0,
+            pawn_key: 0,
+            material_key: 0,
+
+            material_score: 0,
+            square_table_score: 0,
         }
     }
 }
@@ -199,142 +241,116 @@ impl Board {
         }
     }
 
-    pub fn load_position(&mut self, fen: Option<UciFen>, moves: Vec<UciMove>) {
-        self.clean_board();
+    pub fn load_position(
+        &mut self,
+        fen: Option<UciFen>,
+        moves: Vec<UciMove>,
+    ) -> Result<(), FenError> {
+        let mut board = match fen {
+            Some(UciFen(fen)) => Board::from_fen(&fen)?,
+            None => Board::from_fen(STARTPOS)?,
+        };
+        board.chess960 = self.chess960;
 
-        if fen.is_none() {
-            self.load_startpos();
-        } else {
-            match fen {
-                Some(UciFen(fen)) => self.load_fen(fen),
-                None => unreachable!(),
-            }
+        for ucimove in moves {
+            let m = Move::from_ucimove(&board, ucimove);
+            board.make_move(m);
         }
 
-        for ucimove in moves {
-            let m = Move::from_ucimove(self, ucimove);
-            self.make_move(m);
-        }
-
-        self.calculate_zobrist();
-    }
-
-    fn load_startpos(&mut self) {
-        self.white_pieces = 0xffff;
-        self.black_pieces = 0xffff << 48;
-
-        self.pawns = 0xff00 | (0x00ff << 48);
-        self.rooks = 0x0081 | (0x8100 << 48);
-        self.knights = 0x0042 | (0x4200 << 48);
-        self.bishops = 0x0024 | (0x2400 << 48);
-        self.queens = 0x0008 | (0x0800 << 48);
-        self.kings = 0x0010 | (0x1000 << 48);
-
-        self.turn = Color::White;
-        self.half_move_clock = 0;
-        self.full_move_clock = 1;
-    }
-
-    fn load_fen(&mut self, fen: String) {
-        let mut parts = fen.split(" ");
-        let pieces = parts.next().unwrap();
-        let turn = parts.next().unwrap();
-        let castling = parts.next().unwrap();
-        let en_passant = parts.next().unwrap();
-        let halfmove_clock = parts.next().unwrap();
-        let fullmove_clock = parts.next().unwrap();
-        let mut pos: Square = 56;
-
-        for piece in pieces.chars() {
-            if piece == '/' {
-                continue;
-            } else if piece.is_ascii_digit() {
-                pos += piece as Square - '0' as Square;
-            } else {
-                if piece.is_uppercase() {
-                    self.white_pieces |= 1 << pos;
-                    match piece {
-                        'P' => self.pawns |= 1 << pos,
-                        'R' => self.rooks |= 1 << pos,
-                        'N' => self.knights |= 1 << pos,
-                        'B' => self.bishops |= 1 << pos,
-                        'Q' => self.queens |= 1 << pos,
-                        'K' => self.kings |= 1 << pos,
-                        _ => (),
-                    }
-                } else {
-                    self.black_pieces |= 1 << pos;
-                    match piece {
-                        'p' => self.pawns |= 1 << pos,
-                        'r' => self.rooks |= 1 << pos,
-                        'n' => self.knights |= 1 << pos,
-                        'b' => self.bishops |= 1 << pos,
-                        'q' => self.queens |= 1 << pos,
-                        'k' => self.kings |= 1 << pos,
-                        _ => (),
-                    }
+        *self = board;
+        Ok(())
+    }
+
+    pub fn calculate_zobrist(&mut self) {
+        self.zobrist_hash = 0;
+        self.pawn_key = 0;
+        self.material_key = 0;
+        for square in 0..64 {
+            let piece = self.get_piece(square);
+            if piece.kind != PieceKind::None {
+                self.zobrist_hash ^= zobrist::piece(piece, square);
+                if piece.kind == PieceKind::Pawn || piece.kind == PieceKind::King {
+                    self.pawn_key ^= zobrist::piece(piece, square);
                 }
-                pos += 1;
             }
-            if pos > 8 && pos % 8 == 0 {
-                pos -= 16;
-            }
-        }
-
-        match turn {
-            "w" => self.turn = Color::White,
-            "b" => self.turn = Color::Black,
-            _ => panic!("Fen needs a turn!"),
         }
 
-        self.castling_rights = 0;
-        for castling_right in castling.chars() {
-            match castling_right {
-                'K' => self.castling_rights |= 0b1000,
-                'Q' => self.castling_rights |= 0b0100,
-                'k' => self.castling_rights |= 0b0010,
-                'q' => self.castling_rights |= 0b0001,
-                _ => (),
+        for color in [Color::White, Color::Black] {
+            for kind in [
+                PieceKind::Pawn,
+                PieceKind::Rook,
+                PieceKind::Knight,
+                PieceKind::Bishop,
+                PieceKind::Queen,
+                PieceKind::King,
+            ] {
+                let piece = Piece { color, kind };
+                for count in 0..self.count(piece) {
+                    self.material_key ^= zobrist::material(piece, count);
+                }
             }
         }
 
-        if en_passant != "-" {
-            self.ep = en_passant.to_string().to_square();
-        } else {
-            self.ep = -1;
+        if self.turn == Color::White {
+            self.zobrist_hash ^= zobrist::turn();
         }
 
-        self.half_move_clock = halfmove_clock.parse().unwrap();
-        self.full_move_clock = fullmove_clock.parse().unwrap();
+        self.zobrist_hash ^= zobrist::castling(self.castling_rights);
+        self.zobrist_hash ^= self.en_passant_zobrist();
     }
 
-    fn calculate_zobrist(&mut self) {
+    /// The same position with the board flipped vertically and the colors swapped, so it should
+    /// evaluate the same for the side to move. The game history is not kept
+    pub fn mirror(&self) -> Board {
+        let flip = |color| match color {
+            Color::White => Color::Black,
+            Color::Black => Color::White,
+            Color::None => unreachable!(),
+        };
+
+        let mut board = Board::new();
         for square in 0..64 {
             let piece = self.get_piece(square);
             if piece.kind != PieceKind::None {
-                self.zobrist_hash ^= self.zobrist_values
-                    [square as usize + piece.kind as usize * 128 + piece.color as usize * 64];
+                let piece = Piece {
+                    color: flip(piece.color),
+                    kind: piece.kind,
+                };
+                board.toggle_piece(piece, square ^ 56);
             }
         }
 
-        if self.turn == Color::Black {
-            self.zobrist_hash ^= self.zobrist_values[768];
-        }
+        board.turn = flip(self.turn);
+        // White's rights become black's and the other way around
+        board.castling_rights = (self.castling_rights >> 2 | self.castling_rights << 2) & 0b1111;
+        board.castling_rooks = [2, 3, 0, 1].map(|i| self.castling_rooks[i] ^ 56);
+        board.ep = if self.ep == -1 { -1 } else { self.ep ^ 56 };
+        board.half_move_clock = self.half_move_clock;
+        board.full_move_clock = self.full_move_clock;
+        board.chess960 = self.chess960;
 
-        if self.castling_rights & 0b1000 > 0 {
-            self.zobrist_hash ^= self.zobrist_values[769];
-        }
-        if self.castling_rights & 0b0100 > 0 {
-            self.zobrist_hash ^= self.zobrist_values[770];
+        board.calculate_zobrist();
+        board
+    }
+
+    /// Like Polyglot, the en passant file is only hashed if the side to move has a pawn next to
+    /// the pawn that just double-pushed
+    fn en_passant_zobrist(&self) -> u64 {
+        if self.ep == -1 {
+            return 0;
         }
-        if self.castling_rights & 0b0010 > 0 {
-            self.zobrist_hash ^= self.zobrist_values[771];
+        let pushed = self.ep - self.turn;
+        let mut neighbours = 0;
+        if pushed % 8 > 0 {
+            neighbours |= 1 << (pushed - 1);
         }
-        if self.castling_rights & 0b0001 > 0 {
-            self.zobrist_hash ^= self.zobrist_values[772];
+        if pushed % 8 < 7 {
+            neighbours |= 1 << (pushed + 1);
         }
-        if self.ep != -1 {
-            self.zobrist_hash ^= self.zobrist_values[773 + (self.ep % 8) as usize];
+        if neighbours & self.pawns & self.own_pieces() > 0 {
+            zobrist::en_passant(self.ep % 8)
+        } else {
+            0
         }
     }
 
@@ -355,10 +371,18 @@ impl Board {
                 }
             }
             PieceKind::King => {
-                if to - from == 2 {
-                    flags = 2;
-                } else if to - from == -2 {
-                    flags = 3;
+                // Castling is encoded as the king capturing its own rook, outside of Chess960 it
+                // may also be written as the king moving two squares
+                let rook = if to_piece.kind == PieceKind::Rook && to_piece.color == from_piece.color
+                {
+                    Some(to)
+                } else if !self.chess960 && Square::abs(to - from) == 2 {
+                    Some(self.castling_rook(from_piece.color, to > from))
+                } else {
+                    None
+                };
+                if let Some(rook) = rook {
+                    return Move::new(from, rook, if rook > from { 0b0010 } else { 0b0011 });
                 }
             }
             _ => (),
@@ -386,12 +410,15 @@ impl Board {
             ep: self.ep,
             half_move_clock: self.half_move_clock,
             castling_rights: self.castling_rights,
+            zobrist_hash: self.zobrist_hash,
+            null_move: false,
         };
 
         let from = m.from();
         let to = m.to();
 
         let from_piece = self.get_piece(from);
+        self.zobrist_hash ^= self.en_passant_zobrist();
 
         // Capture
         if m.is_capture() && !m.is_en_passant() {
@@ -410,72 +437,34 @@ impl Board {
             irreversible_aspects.capture = ep_piece;
             self.toggle_piece(ep_piece, self.ep - self.turn);
         }
-        if self.ep != -1 {
-            self.zobrist_hash ^= self.zobrist_values[773 + (self.ep % 8) as usize];
-            self.ep = -1;
-        }
+        self.ep = -1;
         if m.is_double_push() {
             self.ep = to - self.turn;
-            self.zobrist_hash ^= self.zobrist_values[773 + (self.ep % 8) as usize];
-        }
-
-        // Promotion
-        if m.is_promotion() {
-            let promotion = m.promotion();
-            self.toggle_promotion(promotion, to);
         }
 
         // Castling
         if m.is_castle() {
-            self.toggle_castle(m, from);
+            self.toggle_castle(m, false);
             match self.turn {
-                Color::White => {
-                    if self.castling_rights & 0b1000 > 0 {
-                        self.castling_rights &= 0b0111;
-                        self.zobrist_hash ^= self.zobrist_values[769];
-                    }
-                    if self.castling_rights & 0b0100 > 0 {
-                        self.castling_rights &= 0b1011;
-                        self.zobrist_hash ^= self.zobrist_values[770];
-                    }
-                }
-                Color::Black => {
-                    if self.castling_rights & 0b0010 > 0 {
-                        self.castling_rights &= 0b1101;
-                        self.zobrist_hash ^= self.zobrist_values[771];
-                    }
-                    if self.castling_rights & 0b0001 > 0 {
-                        self.castling_rights &= 0b1110;
-                        self.zobrist_hash ^= self.zobrist_values[772];
-                    }
-                }
+                Color::White => self.remove_castling_rights(0b1100),
+                Color::Black => self.remove_castling_rights(0b0011),
                 Color::None => unreachable!(),
             }
+        } else {
+            self.move_piece(from_piece, m);
+        }
+
+        // Promotion
+        if m.is_promotion() {
+            let promotion = m.promotion();
+            self.toggle_promotion(promotion, to);
         }
 
         // King moves
         if from_piece.kind == PieceKind::King {
             match self.turn {
-                Color::White => {
-                    if self.castling_rights & 0b1000 > 0 {
-                        self.castling_rights &= 0b0111;
-                        self.zobrist_hash ^= self.zobrist_values[769];
-                    }
-                    if self.castling_rights & 0b0100 > 0 {
-                        self.castling_rights &= 0b1011;
-                        self.zobrist_hash ^= self.zobrist_values[770];
-                    }
-                }
-                Color::Black => {
-                    if self.castling_rights & 0b0010 > 0 {
-                        self.castling_rights &= 0b1101;
-                        self.zobrist_hash ^= self.zobrist_values[771];
-                    }
-                    if self.castling_rights & 0b0001 > 0 {
-                        self.castling_rights &= 0b1110;
-                        self.zobrist_hash ^= self.zobrist_values[772];
-                    }
-                }
+                Color::White => self.remove_castling_rights(0b1100),
+                Color::Black => self.remove_castling_rights(0b0011),
                 Color::None => unreachable!(),
             }
         }
@@ -485,86 +474,125 @@ impl Board {
             self.modify_castling_rights_from_rook(from);
         }
 
-        if m.is_quiet() {
-            self.half_move_clock += 1;
-        } else {
+        if from_piece.kind == PieceKind::Pawn || m.is_capture() {
             self.half_move_clock = 0;
+        } else {
+            self.half_move_clock += 1;
         }
 
-        self.move_piece(from_piece, m);
         self.change_turn();
         if self.turn == Color::White {
             self.full_move_clock += 1;
         }
+        self.zobrist_hash ^= self.en_passant_zobrist();
         self.game_stack.push(irreversible_aspects);
     }
 
     pub fn unmake_move(&mut self, m: Move) {
+        self.zobrist_hash ^= self.en_passant_zobrist();
         self.change_turn();
         let IrreversibleAspects {
             capture,
             ep,
             half_move_clock,
             castling_rights,
+            ..
         } = self.game_stack.pop().unwrap();
 
-        if ep != -1 {
-            self.zobrist_hash ^= self.zobrist_values[773 + (ep % 8) as usize];
-        }
-        if self.ep != -1 {
-            self.zobrist_hash ^= self.zobrist_values[773 + (self.ep % 8) as usize];
-        }
+        self.zobrist_hash ^= zobrist::castling(self.castling_rights ^ castling_rights);
 
-        if self.castling_rights != castling_rights {
-            let modified = self.castling_rights ^ castling_rights;
-            if modified & 0b1000 > 0 {
-                self.zobrist_hash ^= self.zobrist_values[769];
-            }
-            if modified & 0b0100 > 0 {
-                self.zobrist_hash ^= self.zobrist_values[770];
-            }
-            if modified & 0b0010 > 0 {
-                self.zobrist_hash ^= self.zobrist_values[771];
-            }
-            if modified & 0b0001 > 0 {
-                self.zobrist_hash ^= self.zobrist_values[772];
+        // Castling
+        if m.is_castle() {
+            self.toggle_castle(m, true);
+        } else {
+            let m = m.reverse();
+
+            let from = m.from();
+
+            // Promotion
+            if m.is_promotion() {
+                let promotion = m.promotion();
+                self.toggle_promotion(promotion, from);
             }
-        }
 
-        let m = m.reverse();
+            let from_piece = self.get_piece(from);
+            self.move_piece(from_piece, m);
 
-        let from = m.from();
-        let to = m.to();
+            // Capture
+            if m.is_capture() && !m.is_en_passant() {
+                self.toggle_piece(capture, from);
 
-        // Promotion
-        if m.is_promotion() {
-            let promotion = m.promotion();
-            self.toggle_promotion(promotion, from);
+            // En passant
+            } else if m.is_en_passant() {
+                self.toggle_piece(capture, ep - self.turn);
+            }
         }
+        self.ep = ep;
+        self.half_move_clock = half_move_clock;
+        self.castling_rights = castling_rights;
+        if self.turn == Color::Black {
+            self.full_move_clock -= 1;
+        }
+        self.zobrist_hash ^= self.en_passant_zobrist();
+    }
 
-        let from_piece = self.get_piece(from);
-
-        // Capture
-        if m.is_capture() && !m.is_en_passant() {
-            self.toggle_piece(capture, from);
+    /// Passes the turn without moving a piece, e.g. for null move pruning
+    pub fn make_null_move(&mut self) {
+        self.game_stack.push(IrreversibleAspects {
+            capture: Piece::NONE,
+            ep: self.ep,
+            half_move_clock: self.half_move_clock,
+            castling_rights: self.castling_rights,
+            zobrist_hash: self.zobrist_hash,
+            null_move: true,
+        });
 
-        // En passant
-        } else if m.is_en_passant() {
-            self.toggle_piece(capture, ep - self.turn);
+        self.zobrist_hash ^= self.en_passant_zobrist();
+        self.ep = -1;
+        self.half_move_clock += 1;
+        self.change_turn();
+        if self.turn == Color::White {
+            self.full_move_clock += 1;
         }
+    }
 
-        // Castling
-        if m.is_castle() {
-            self.toggle_castle(m, to);
-        }
+    pub fn unmake_null_move(&mut self) {
+        self.change_turn();
+        let IrreversibleAspects {
+            ep,
+            half_move_clock,
+            ..
+        } = self.game_stack.pop().unwrap();
 
-        self.move_piece(from_piece, m);
         self.ep = ep;
         self.half_move_clock = half_move_clock;
-        self.castling_rights = castling_rights;
         if self.turn == Color::Black {
             self.full_move_clock -= 1;
         }
+        self.zobrist_hash ^= self.en_passant_zobrist();
+    }
+
+    /// Whether the current position has occurred before since the last irreversible move
+    pub fn is_repetition(&self) -> bool {
+        self.repetitions() >= 1
+    }
+
+    pub fn is_threefold(&self) -> bool {
+        self.repetitions() >= 2
+    }
+
+    fn repetitions(&self) -> usize {
+        // Only positions with the same side to move, and no pawn move, capture or null move in
+        // between, can repeat
+        self.game_stack
+            .iter()
+            .rev()
+            .take(self.half_move_clock as usize)
+            .take_while(|aspects| !aspects.null_move)
+            .skip(1)
+            .step_by(2)
+            .filter(|aspects| aspects.zobrist_hash == self.zobrist_hash)
+            .count()
     }
 
     fn move_piece(&mut self, piece: Piece, m: Move) {
@@ -585,13 +613,19 @@ impl Board {
             PieceKind::None => unreachable!(),
         }
 
-        self.zobrist_hash ^= self.zobrist_values
-            [m.from() as usize + piece.kind as usize * 128 + piece.color as usize * 64];
-        self.zobrist_hash ^= self.zobrist_values
-            [m.to() as usize + piece.kind as usize * 128 + piece.color as usize * 64];
+        self.mailbox[m.from() as usize] = Piece::NONE;
+        self.mailbox[m.to() as usize] = piece;
+
+        self.square_table_score += psqt::value(piece, m.to()) - psqt::value(piece, m.from());
+
+        let key = zobrist::piece(piece, m.from()) ^ zobrist::piece(piece, m.to());
+        self.zobrist_hash ^= key;
+        if piece.kind == PieceKind::Pawn || piece.kind == PieceKind::King {
+            self.pawn_key ^= key;
+        }
     }
 
-    fn toggle_piece(&mut self, piece: Piece, square: Square) {
+    pub fn toggle_piece(&mut self, piece: Piece, square: Square) {
         let bitmap = 1 << square;
         match piece.color {
             Color::White => self.white_pieces ^= bitmap,
@@ -609,113 +643,121 @@ impl Board {
             PieceKind::None => unreachable!(),
         }
 
-        self.zobrist_hash ^= self.zobrist_values
-            [square as usize + piece.kind as usize * 128 + piece.color as usize * 64];
+        // The square is either empty or holds this exact piece
+        let count = self.count(piece);
+        if self.mailbox[square as usize] == piece {
+            self.mailbox[square as usize] = Piece::NONE;
+            self.material_score -= piece.score();
+            self.square_table_score -= psqt::value(piece, square);
+            self.material_key ^= zobrist::material(piece, count);
+        } else {
+            self.mailbox[square as usize] = piece;
+            self.material_score += piece.score();
+            self.square_table_score += psqt::value(piece, square);
+            self.material_key ^= zobrist::material(piece, count - 1);
+        }
+
+        self.zobrist_hash ^= zobrist::piece(piece, square);
+        if piece.kind == PieceKind::Pawn || piece.kind == PieceKind::King {
+            self.pawn_key ^= zobrist::piece(piece, square);
+        }
+    }
+
+    /// How many of the piece there are on the board
+    pub fn count(&self, piece: Piece) -> u32 {
+        let pieces = match piece.color {
+            Color::White => self.white_pieces,
+            Color::Black => self.black_pieces,
+            Color::None => unreachable!(),
+        };
+        let kind = match piece.kind {
+            PieceKind::Pawn => self.pawns,
+            PieceKind::Rook => self.rooks,
+            PieceKind::Knight => self.knights,
+            PieceKind::Bishop => self.bishops,
+            PieceKind::Queen => self.queens,
+            PieceKind::King => self.kings,
+            PieceKind::None => unreachable!(),
+        };
+        (pieces & kind).count_ones()
     }
 
     fn toggle_promotion(&mut self, piecekind: PieceKind, square: Square) {
-        self.toggle_piece(
-            Piece {
-                color: self.turn,
-                kind: PieceKind::Pawn,
-            },
-            square,
-        );
-        self.toggle_piece(
-            Piece {
-                color: self.turn,
-                kind: piecekind,
-            },
-            square,
-        );
-    }
-
-    fn toggle_castle(&mut self, m: Move, from: i16) {
-        let castle = m.castle();
-        match castle {
-            CastleKind::KingSide => self.move_piece(
-                Piece {
-                    color: self.turn,
-                    kind: PieceKind::Rook,
-                },
-                Move::new(from + 3, from + 1, 0),
-            ),
-            CastleKind::QueenSide => self.move_piece(
-                Piece {
-                    color: self.turn,
-                    kind: PieceKind::Rook,
-                },
-                Move::new(from - 4, from - 1, 0),
-            ),
-            CastleKind::None => unreachable!(),
+        let pawn = Piece {
+            color: self.turn,
+            kind: PieceKind::Pawn,
+        };
+        let promoted = Piece {
+            color: self.turn,
+            kind: piecekind,
+        };
+        // Whichever piece is on the square has to be lifted first to keep the mailbox right
+        if self.mailbox[square as usize] == pawn {
+            self.toggle_piece(pawn, square);
+            self.toggle_piece(promoted, square);
+        } else {
+            self.toggle_piece(promoted, square);
+            self.toggle_piece(pawn, square);
+        }
+    }
+
+    fn toggle_castle(&mut self, m: Move, undo: bool) {
+        let king = Piece {
+            color: self.turn,
+            kind: PieceKind::King,
+        };
+        let rook = Piece {
+            color: self.turn,
+            kind: PieceKind::Rook,
+        };
+        let (mut king_squares, mut rook_squares) =
+            ((m.from(), m.castle_king_to()), (m.to(), m.castle_rook_to()));
+        if undo {
+            king_squares = (king_squares.1, king_squares.0);
+            rook_squares = (rook_squares.1, rook_squares.0);
+        }
+        // In Chess960 the king and rook can land on each other's squares, so both are lifted
+        // before either is placed
+        self.toggle_piece(king, king_squares.0);
+        self.toggle_piece(rook, rook_squares.0);
+        self.toggle_piece(king, king_squares.1);
+        self.toggle_piece(rook, rook_squares.1);
+    }
+
+    pub fn castling_rook(&self, color: Color, king_side: bool) -> Square {
+        match (color, king_side) {
+            (Color::White, true) => self.castling_rooks[0],
+            (Color::White, false) => self.castling_rooks[1],
+            (Color::Black, true) => self.castling_rooks[2],
+            (Color::Black, false) => self.castling_rooks[3],
+            (Color::None, _) => unreachable!(),
         }
     }
 
     fn modify_castling_rights_from_rook(&mut self, from: i16) {
-        match from {
-            0 => {
-                if self.castling_rights & 0b0100 > 0 {
-                    self.castling_rights &= 0b1011;
-                    self.zobrist_hash ^= self.zobrist_values[770];
-                }
+        for (i, castling_right) in [0b1000, 0b0100, 0b0010, 0b0001].into_iter().enumerate() {
+            if self.castling_rooks[i] == from {
+                self.remove_castling_rights(castling_right);
             }
-            7 => {
-                if self.castling_rights & 0b1000 > 0 {
-                    self.castling_rights &= 0b0111;
-                    self.zobrist_hash ^= self.zobrist_values[769];
-                }
-            }
-            56 => {
-                if self.castling_rights & 0b0001 > 0 {
-                    self.castling_rights &= 0b1110;
-                    self.zobrist_hash ^= self.zobrist_values[772];
-                }
-            }
-            63 => {
-                if self.castling_rights & 0b0010 > 0 {
-                    self.castling_rights &= 0b1101;
-                    self.zobrist_hash ^= self.zobrist_values[771];
-                }
-            }
-            _ => (),
         }
     }
 
+    fn remove_castling_rights(&mut self, castling_rights: u8) {
+        self.zobrist_hash ^= zobrist::castling(self.castling_rights & castling_rights);
+        self.castling_rights &= !castling_rights;
+    }
+
     pub fn change_turn(&mut self) {
         if self.turn == Color::White {
             self.turn = Color::Black;
         } else {
             self.turn = Color::White;
         }
-        self.zobrist_hash ^= self.zobrist_values[768];
+        self.zobrist_hash ^= zobrist::turn();
     }
 
     pub fn get_piece(&self, square: Square) -> Piece {
-        let color = if (self.white_pieces & (1 << square)) > 0 {
-            Color::White
-        } else if (self.black_pieces & (1 << square)) > 0 {
-            Color::Black
-        } else {
-            return Piece::NONE;
-        };
-
-        let kind = if (self.pawns & (1 << square)) > 0 {
-            PieceKind::Pawn
-        } else if (self.rooks & (1 << square)) > 0 {
-            PieceKind::Rook
-        } else if (self.knights & (1 << square)) > 0 {
-            PieceKind::Knight
-        } else if (self.bishops & (1 << square)) > 0 {
-            PieceKind::Bishop
-        } else if (self.queens & (1 << square)) > 0 {
-            PieceKind::Queen
-        } else if (self.kings & (1 << square)) > 0 {
-            PieceKind::King
-        } else {
-            unreachable!()
-        };
-
-        Piece { color, kind }
+        self.mailbox[square as usize]
     }
 
     #[allow(dead_code)]
@@ -751,20 +793,6 @@ impl Board {
         }
         eprintln!("{string}");
     }
-
-    fn clean_board(&mut self) {
-        self.white_pieces = 0;
-        self.black_pieces = 0;
-
-        self.pawns = 0;
-        self.rooks = 0;
-        self.knights = 0;
-        self.bishops = 0;
-        self.queens = 0;
-        self.kings = 0;
-
-        self.ep = -1;
-    }
 }
 
 pub fn piece_to_ascii(piece: Piece) -> char {
@@ -0,0 +1,105 @@
+use std::{fs, io, path::Path};
+
+use crate::{
+    board::{Board, PieceKind, Square},
+    r#move::Move,
+};
+
+#[derive(Debug, Clone, Copy)]
+struct BookEntry {
+    key: u64,
+    m: u16,
+    weight: u16,
+}
+
+/// A Polyglot opening book
+#[derive(Debug, Clone)]
+pub struct Book {
+    // Sorted by key, as in the file
+    entries: Vec<BookEntry>,
+}
+
+impl Book {
+    pub fn open<P: AsRef<Path>>(path: P) -> io::Result<Book> {
+        let data = fs::read(path)?;
+        if data.len() % 16 != 0 {
+            return Err(io::Error::new(
+                io::ErrorKind::InvalidData,
+                "Polyglot books consist of 16 byte entries",
+            ));
+        }
+
+        // key: u64 | move: u16 | weight: u16 | learn: u32, all big endian
+        let entries = data
+            .chunks_exact(16)
+            .map(|entry| BookEntry {
+                key: u64::from_be_bytes(entry[0..8].try_into().unwrap()),
+                m: u16::from_be_bytes(entry[8..10].try_into().unwrap()),
+                weight: u16::from_be_bytes(entry[10..12].try_into().unwrap()),
+            })
+            .collect();
+
+        Ok(Book { entries })
+    }
+
+    /// All legal book moves in the position along with their weights
+    pub fn moves(&self, board: &mut Board) -> Vec<(Move, u16)> {
+        let key = board.zobrist_hash;
+        let start = self.entries.partition_point(|entry| entry.key < key);
+
+        let mut moves = Vec::new();
+        for entry in self.entries[start..]
+            .iter()
+            .take_while(|entry| entry.key == key)
+        {
+            if let Some(m) = Self::decode_move(board, entry.m) {
+                moves.push((m, entry.weight));
+            }
+        }
+        moves
+    }
+
+    /// Picks a book move, either the one with the highest weight or a random one weighted by the
+    /// entry weights
+    pub fn probe(&self, board: &mut Board, best: bool) -> Option<Move> {
+        let moves = self.moves(board);
+        if best {
+            return moves
+                .iter()
+                .max_by_key(|(_, weight)| *weight)
+                .map(|(m, _)| *m);
+        }
+
+        let total = moves.iter().map(|(_, weight)| *weight as u32).sum::<u32>();
+        if total == 0 {
+            return moves.first().map(|(m, _)| *m);
+        }
+        let mut choice = rand::random_range(0..total);
+        for (m, weight) in moves {
+            if choice < weight as u32 {
+                return Some(m);
+            }
+            choice -= weight as u32;
+        }
+        unreachable!()
+    }
+
+    // to file | to row | from file | from row | promotion, 3 bits each
+    fn decode_move(board: &mut Board, m: u16) -> Option<Move> {
+        let to = (m & 0b111111) as Square;
+        let from = ((m >> 6) & 0b111111) as Square;
+        let promotion = match (m >> 12) & 0b111 {
+            0 => PieceKind::None,
+            1 => PieceKind::Knight,
+            2 => PieceKind::Bishop,
+            3 => PieceKind::Rook,
+            4 => PieceKind::Queen,
+            _ => return None,
+        };
+
+        // Polyglot encodes castling as the king capturing its own rook, just like we do
+        let m = board.annotate_move(Move::new(from, to, 0), promotion);
+        // Guard against hash collisions and corrupt books
+        board.generate_moves().find(|legal| *legal == m)
+    }
+}
@@ -0,0 +1,188 @@
+use crate::{
+    board::Board,
+    fen::FenError,
+    r#move::Move,
+    san::SanError,
+    validate::PositionError,
+};
+
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum EpdError {
+    Fen(FenError),
+    IllegalPosition(PositionError),
+    UnterminatedString,
+    MissingOperand(String),
+    InvalidOperand { opcode: String, operand: String },
+    InvalidMove { opcode: String, err: SanError },
+}
+
+impl std::fmt::Display for EpdError {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        match self {
+            EpdError::Fen(err) => write!(f, "invalid position: {err}"),
+            EpdError::IllegalPosition(err) => write!(f, "illegal position: {err}"),
+            EpdError::UnterminatedString => write!(f, "unterminated string operand"),
+            EpdError::MissingOperand(opcode) => write!(f, "missing operand for '{opcode}'"),
+            EpdError::InvalidOperand { opcode, operand } => {
+                write!(f, "invalid operand '{operand}' for '{opcode}'")
+            }
+            EpdError::InvalidMove { opcode, err } => write!(f, "{opcode}: {err}"),
+        }
+    }
+}
+
+impl std::error::Error for EpdError {}
+
+/// A position from an EPD line along with its operations
+#[derive(Debug, Clone)]
+pub struct Epd {
+    pub board: Board,
+    pub id: Option<String>,
+    pub best_moves: Vec<Move>,
+    pub avoid_moves: Vec<Move>,
+    // Centipawn evaluation
+    pub ce: Option<i64>,
+    // Analysis count depth
+    pub acd: Option<u8>,
+    // Perft node counts by depth, from D1, D2 and so on
+    pub perft: Vec<(u8, usize)>,
+    // Every operation in the order they appear, the ones above included
+    pub operations: Vec<(String, Vec<String>)>,
+}
+
+// Operands are separated by whitespace, except inside quoted strings
+fn split_operations(operations: &str) -> Result<Vec<(String, Vec<String>)>, EpdError> {
+    let mut result = Vec::new();
+    let mut words = Vec::new();
+    let mut word = String::new();
+    let mut in_string = false;
+
+    for chr in operations.chars().chain(std::iter::once(';')) {
+        match chr {
+            '"' => {
+                if in_string {
+                    words.push(std::mem::take(&mut word));
+                }
+                in_string = !in_string;
+            }
+            _ if in_string => word.push(chr),
+            ';' => {
+                if !word.is_empty() {
+                    words.push(std::mem::take(&mut word));
+                }
+                if !words.is_empty() {
+                    let opcode = words.remove(0);
+                    result.push((opcode, std::mem::take(&mut words)));
+                }
+            }
+            chr if chr.is_whitespace() => {
+                if !word.is_empty() {
+                    words.push(std::mem::take(&mut word));
+                }
+            }
+            chr => word.push(chr),
+        }
+    }
+    if in_string {
+        return Err(EpdError::UnterminatedString);
+    }
+
+    Ok(result)
+}
+
+fn parse_number<T: std::str::FromStr>(opcode: &str, operands: &[String]) -> Result<T, EpdError> {
+    let operand = operands
+        .first()
+        .ok_or_else(|| EpdError::MissingOperand(opcode.to_string()))?;
+    operand.parse().map_err(|_| EpdError::InvalidOperand {
+        opcode: opcode.to_string(),
+        operand: operand.to_string(),
+    })
+}
+
+impl Epd {
+    pub fn parse(line: &str) -> Result<Epd, EpdError> {
+        let line = line.trim();
+        // The start and end of the field after `end`
+        let next_field = |end: usize| {
+            let rest = &line[end..];
+            let start = end + rest.len() - rest.trim_start().len();
+            let field_end = line[start..]
+                .find(|chr: char| chr.is_whitespace() || chr == ';')
+                .map_or(line.len(), |i| start + i);
+            (start, field_end)
+        };
+
+        // The position is the first four FEN fields, the clocks are given as operations
+        let mut end = 0;
+        for _ in 0..4 {
+            end = next_field(end).1;
+        }
+        let board = Board::from_fen(&line[..end]).map_err(EpdError::Fen)?;
+        board.validate().map_err(EpdError::IllegalPosition)?;
+
+        // Some files keep the FEN clock fields anyway, they are read as hmvc and fmvn
+        let mut clocks = Vec::new();
+        let (halfmove_start, halfmove_end) = next_field(end);
+        let (fullmove_start, fullmove_end) = next_field(halfmove_end);
+        let halfmove = &line[halfmove_start..halfmove_end];
+        let fullmove = &line[fullmove_start..fullmove_end];
+        if [halfmove, fullmove]
+            .iter()
+            .all(|field| !field.is_empty() && field.chars().all(|chr| chr.is_ascii_digit()))
+        {
+            clocks.push((String::from("hmvc"), vec![halfmove.to_string()]));
+            clocks.push((String::from("fmvn"), vec![fullmove.to_string()]));
+            end = fullmove_end;
+        }
+        let mut operations = split_operations(&line[end..])?;
+        operations.splice(0..0, clocks);
+
+        let mut epd = Epd {
+            board,
+            id: None,
+            best_moves: Vec::new(),
+            avoid_moves: Vec::new(),
+            ce: None,
+            acd: None,
+            perft: Vec::new(),
+            operations,
+        };
+
+        for (opcode, operands) in epd.operations.clone() {
+            match opcode.as_str() {
+                "id" => epd.id = operands.first().cloned(),
+                "bm" | "am" => {
+                    let mut moves = Vec::new();
+                    for san in &operands {
+                        moves.push(epd.board.parse_san(san).map_err(|err| {
+                            EpdError::InvalidMove {
+                                opcode: opcode.clone(),
+                                err,
+                            }
+                        })?);
+                    }
+                    match opcode.as_str() {
+                        "bm" => epd.best_moves = moves,
+                        _ => epd.avoid_moves = moves,
+                    }
+                }
+                "ce" => epd.ce = Some(parse_number(&opcode, &operands)?),
+                "acd" => epd.acd = Some(parse_number(&opcode, &operands)?),
+                "hmvc" => epd.board.half_move_clock = parse_number(&opcode, &operands)?,
+                "fmvn" => epd.board.full_move_clock = parse_number(&opcode, &operands)?,
+                _ => {
+                    if let Some(depth) = opcode
+                        .strip_prefix('D')
+                        .and_then(|depth| depth.parse().ok())
+                    {
+                        epd.perft
+                            .push((depth, parse_number(&opcode, &operands)?));
+                    }
+                }
+            }
+        }
+
+        Ok(epd)
+    }
+}
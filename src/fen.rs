@@ -0,0 +1,302 @@
+use crate::board::{
+    AsSquare, Board, Color, Piece, PieceKind, Square, parse_square, piece_to_ascii,
+};
+
+pub const STARTPOS: &str = "rnbqkbnr/pppppppp/8/8/8/8/PPPPPPPP/RNBQKBNR w KQkq - 0 1";
+
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum FenError {
+    MissingField(&'static str),
+    TooManyFields,
+    WrongRankCount(usize),
+    TooManySquares { rank: u8 },
+    TooFewSquares { rank: u8 },
+    UnknownPiece(char),
+    InvalidTurn(String),
+    InvalidCastling(String),
+    InvalidEnPassant(String),
+    EnPassantWrongRank(String),
+    InvalidHalfMoveClock(String),
+    InvalidFullMoveClock(String),
+}
+
+impl std::fmt::Display for FenError {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        match self {
+            FenError::MissingField(field) => write!(f, "missing {field} field"),
+            FenError::TooManyFields => write!(f, "too many fields"),
+            FenError::WrongRankCount(ranks) => write!(f, "expected 8 ranks, found {ranks}"),
+            FenError::TooManySquares { rank } => write!(f, "too many squares in rank {rank}"),
+            FenError::TooFewSquares { rank } => write!(f, "too few squares in rank {rank}"),
+            FenError::UnknownPiece(piece) => write!(f, "unknown piece '{piece}'"),
+            FenError::InvalidTurn(turn) => write!(f, "invalid side to move '{turn}'"),
+            FenError::InvalidCastling(castling) => {
+                write!(f, "invalid castling rights '{castling}'")
+            }
+            FenError::InvalidEnPassant(ep) => write!(f, "invalid en passant square '{ep}'"),
+            FenError::EnPassantWrongRank(ep) => {
+                write!(f, "en passant square '{ep}' is on the wrong rank")
+            }
+            FenError::InvalidHalfMoveClock(clock) => {
+                write!(f, "invalid halfmove clock '{clock}'")
+            }
+            FenError::InvalidFullMoveClock(clock) => {
+                write!(f, "invalid fullmove number '{clock}'")
+            }
+        }
+    }
+}
+
+impl std::error::Error for FenError {}
+
+fn ascii_to_piece(chr: char) -> Option<Piece> {
+    let kind = match chr.to_ascii_lowercase() {
+        'p' => PieceKind::Pawn,
+        'r' => PieceKind::Rook,
+        'n' => PieceKind::Knight,
+        'b' => PieceKind::Bishop,
+        'q' => PieceKind::Queen,
+        'k' => PieceKind::King,
+        _ => return None,
+    };
+    let color = if chr.is_ascii_uppercase() {
+        Color::White
+    } else {
+        Color::Black
+    };
+    Some(Piece { color, kind })
+}
+
+fn back_rank(color: Color) -> Square {
+    match color {
+        Color::White => 0,
+        Color::Black => 56,
+        Color::None => unreachable!(),
+    }
+}
+
+impl Board {
+    pub fn from_fen(fen: &str) -> Result<Board, FenError> {
+        let mut board = Board::new();
+
+        let mut parts = fen.split_whitespace();
+        let pieces = parts
+            .next()
+            .ok_or(FenError::MissingField("piece placement"))?;
+        let turn = parts.next().ok_or(FenError::MissingField("side to move"))?;
+        let castling = parts.next().ok_or(FenError::MissingField("castling"))?;
+        let en_passant = parts.next().ok_or(FenError::MissingField("en passant"))?;
+        // The clocks are frequently left out (e.g. in EPD), so they are optional
+        let halfmove_clock = parts.next().unwrap_or("0");
+        let fullmove_clock = parts.next().unwrap_or("1");
+        if parts.next().is_some() {
+            return Err(FenError::TooManyFields);
+        }
+
+        board.load_pieces(pieces)?;
+
+        board.turn = match turn {
+            "w" => Color::White,
+            "b" => Color::Black,
+            _ => return Err(FenError::InvalidTurn(turn.to_string())),
+        };
+
+        board.castling_rights = 0;
+        board.castling_rooks = [7, 0, 63, 56];
+        if castling != "-" {
+            for castling_right in castling.chars() {
+                let color = if castling_right.is_ascii_uppercase() {
+                    Color::White
+                } else {
+                    Color::Black
+                };
+                let back_rank = back_rank(color);
+                // A missing king or rook is left for validate to report
+                let (king_side, rook) = match castling_right.to_ascii_lowercase() {
+                    'k' => (
+                        true,
+                        board.outermost_rook(color, true).unwrap_or(back_rank + 7),
+                    ),
+                    'q' => (false, board.outermost_rook(color, false).unwrap_or(back_rank)),
+                    // Shredder-FEN and X-FEN name the file of the rook
+                    file @ 'a'..='h' => {
+                        let rook = back_rank + (file as u8 - b'a') as Square;
+                        let king = board.back_rank_king(color).unwrap_or(back_rank + 4);
+                        (rook > king, rook)
+                    }
+                    _ => return Err(FenError::InvalidCastling(castling.to_string())),
+                };
+                let index = match (color, king_side) {
+                    (Color::White, true) => 0,
+                    (Color::White, false) => 1,
+                    (Color::Black, true) => 2,
+                    (Color::Black, false) => 3,
+                    (Color::None, _) => unreachable!(),
+                };
+                let bit = 0b1000 >> index;
+                if board.castling_rights & bit > 0 {
+                    return Err(FenError::InvalidCastling(castling.to_string()));
+                }
+                board.castling_rights |= bit;
+                board.castling_rooks[index] = rook;
+            }
+        }
+
+        board.ep = if en_passant == "-" {
+            -1
+        } else {
+            let ep = parse_square(en_passant)
+                .ok_or(FenError::InvalidEnPassant(en_passant.to_string()))?;
+            let ep_rank = match board.turn {
+                Color::White => 5,
+                Color::Black => 2,
+                Color::None => unreachable!(),
+            };
+            if ep / 8 != ep_rank {
+                return Err(FenError::EnPassantWrongRank(en_passant.to_string()));
+            }
+            ep
+        };
+
+        board.half_move_clock = halfmove_clock
+            .parse()
+            .map_err(|_| FenError::InvalidHalfMoveClock(halfmove_clock.to_string()))?;
+        board.full_move_clock = fullmove_clock
+            .parse()
+            .map_err(|_| FenError::InvalidFullMoveClock(fullmove_clock.to_string()))?;
+
+        board.calculate_zobrist();
+        Ok(board)
+    }
+
+    fn load_pieces(&mut self, pieces: &str) -> Result<(), FenError> {
+        let ranks = pieces.split('/').collect::<Vec<_>>();
+        if ranks.len() != 8 {
+            return Err(FenError::WrongRankCount(ranks.len()));
+        }
+
+        for (i, rank) in ranks.into_iter().enumerate() {
+            let rank_number = 8 - i as u8;
+            let mut file = 0;
+            for chr in rank.chars() {
+                if let Some(empty) = chr.to_digit(10).filter(|empty| (1..=8).contains(empty)) {
+                    file += empty as i16;
+                } else {
+                    let piece = ascii_to_piece(chr).ok_or(FenError::UnknownPiece(chr))?;
+                    if file >= 8 {
+                        return Err(FenError::TooManySquares { rank: rank_number });
+                    }
+                    self.toggle_piece(piece, (7 - i as i16) * 8 + file);
+                    file += 1;
+                }
+                if file > 8 {
+                    return Err(FenError::TooManySquares { rank: rank_number });
+                }
+            }
+            if file < 8 {
+                return Err(FenError::TooFewSquares { rank: rank_number });
+            }
+        }
+
+        Ok(())
+    }
+
+    fn back_rank_king(&self, color: Color) -> Option<Square> {
+        let king = Piece {
+            color,
+            kind: PieceKind::King,
+        };
+        let back_rank = back_rank(color);
+        (back_rank..back_rank + 8).find(|&square| self.get_piece(square) == king)
+    }
+
+    // X-FEN uses KQkq for the outermost rook on either side of the king
+    fn outermost_rook(&self, color: Color, king_side: bool) -> Option<Square> {
+        let rook = Piece {
+            color,
+            kind: PieceKind::Rook,
+        };
+        let back_rank = back_rank(color);
+        let king = self.back_rank_king(color)?;
+        if king_side {
+            (king + 1..back_rank + 8)
+                .rev()
+                .find(|&square| self.get_piece(square) == rook)
+        } else {
+            (back_rank..king).find(|&square| self.get_piece(square) == rook)
+        }
+    }
+
+    pub fn to_fen(&self) -> String {
+        let mut fen = String::new();
+
+        for rank in (0..8).rev() {
+            let mut empty = 0;
+            for file in 0..8 {
+                let piece = self.get_piece(rank * 8 + file);
+                if piece.kind == PieceKind::None {
+                    empty += 1;
+                    continue;
+                }
+                if empty > 0 {
+                    fen.push((b'0' + empty) as char);
+                    empty = 0;
+                }
+                fen.push(piece_to_ascii(piece));
+            }
+            if empty > 0 {
+                fen.push((b'0' + empty) as char);
+            }
+            if rank > 0 {
+                fen.push('/');
+            }
+        }
+
+        fen.push(' ');
+        fen.push(match self.turn {
+            Color::White => 'w',
+            Color::Black => 'b',
+            Color::None => unreachable!(),
+        });
+
+        fen.push(' ');
+        if self.castling_rights == 0 {
+            fen.push('-');
+        } else {
+            for (i, (color, king_side)) in [
+                (Color::White, true),
+                (Color::White, false),
+                (Color::Black, true),
+                (Color::Black, false),
+            ]
+            .into_iter()
+            .enumerate()
+            {
+                if self.castling_rights & (0b1000 >> i) == 0 {
+                    continue;
+                }
+                let rook = self.castling_rooks[i];
+                let right = if self.outermost_rook(color, king_side) == Some(rook) {
+                    if king_side { 'k' } else { 'q' }
+                } else {
+                    (b'a' + (rook % 8) as u8) as char
+                };
+                fen.push(match color {
+                    Color::White => right.to_ascii_uppercase(),
+                    _ => right,
+                });
+            }
+        }
+
+        fen.push(' ');
+        if self.ep == -1 {
+            fen.push('-');
+        } else {
+            fen += &self.ep.as_square();
+        }
+
+        fen += &format!(" {} {}", self.half_move_clock, self.full_move_clock);
+
+        fen
+    }
+}
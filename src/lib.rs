@@ -0,0 +1,18 @@
+pub mod board;
+pub mod book;
+pub mod epd;
+pub mod fen;
+mod magic;
+pub mod r#move;
+pub mod move_generator;
+pub mod outcome;
+pub mod packed;
+pub mod perft;
+pub mod pgn;
+mod psqt;
+pub mod san;
+pub mod search;
+pub mod search_test;
+pub mod uci;
+pub mod validate;
+pub mod zobrist;
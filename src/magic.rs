@@ -0,0 +1,311 @@
+use crate::board::{Bitboard, Square};
+
+#[cfg(all(feature = "pext", target_arch = "x86_64"))]
+use std::sync::LazyLock;
+
+// Fancy magic bitboards: every square gets its own slice of a shared attack table, indexed by
+// multiplying the relevant blockers with a magic number and keeping the top bits
+
+const ROOK_DIRS: [(Square, Square); 4] = [(0, 1), (0, -1), (1, 0), (-1, 0)];
+const BISHOP_DIRS: [(Square, Square); 4] = [(1, 1), (1, -1), (-1, 1), (-1, -1)];
+
+const ROOK_TABLE_SIZE: usize = 102400;
+const BISHOP_TABLE_SIZE: usize = 5248;
+
+#[derive(Clone, Copy)]
+struct Magic {
+    mask: Bitboard,
+    magic: u64,
+    shift: u32,
+    offset: usize,
+}
+
+impl Magic {
+    const EMPTY: Magic = Magic {
+        mask: 0,
+        magic: 0,
+        shift: 0,
+        offset: 0,
+    };
+
+    const fn index(&self, occupied: Bitboard) -> usize {
+        self.offset + ((occupied & self.mask).wrapping_mul(self.magic) >> self.shift) as usize
+    }
+
+    #[cfg(all(feature = "pext", target_arch = "x86_64"))]
+    #[target_feature(enable = "bmi2")]
+    fn pext_index(&self, occupied: Bitboard) -> usize {
+        self.offset + std::arch::x86_64::_pext_u64(occupied, self.mask) as usize
+    }
+}
+
+static ROOK_MAGICS: [Magic; 64] = generate_magics(&ROOK_DIRS, &ROOK_MAGIC_NUMBERS);
+static BISHOP_MAGICS: [Magic; 64] = generate_magics(&BISHOP_DIRS, &BISHOP_MAGIC_NUMBERS);
+
+// Filling the rook table takes the const evaluator a while
+#[allow(long_running_const_eval)]
+static ROOK_ATTACKS: [Bitboard; ROOK_TABLE_SIZE] =
+    generate_attacks(&ROOK_DIRS, &ROOK_MAGIC_NUMBERS, false);
+static BISHOP_ATTACKS: [Bitboard; BISHOP_TABLE_SIZE] =
+    generate_attacks(&BISHOP_DIRS, &BISHOP_MAGIC_NUMBERS, false);
+
+/// With the `pext` feature the tables are also built indexed by `_pext_u64` of the blockers
+/// instead of a magic multiplication, and used when the CPU supports BMI2.
+/// The masks and offsets are the same, only the order within each square's slice differs
+#[cfg(all(feature = "pext", target_arch = "x86_64"))]
+#[allow(long_running_const_eval)]
+static ROOK_PEXT_ATTACKS: [Bitboard; ROOK_TABLE_SIZE] =
+    generate_attacks(&ROOK_DIRS, &ROOK_MAGIC_NUMBERS, true);
+#[cfg(all(feature = "pext", target_arch = "x86_64"))]
+static BISHOP_PEXT_ATTACKS: [Bitboard; BISHOP_TABLE_SIZE] =
+    generate_attacks(&BISHOP_DIRS, &BISHOP_MAGIC_NUMBERS, true);
+
+/// Checked once, and not at all when BMI2 is already enabled at compile time
+#[cfg(all(feature = "pext", target_arch = "x86_64"))]
+static BMI2: LazyLock<bool> = LazyLock::new(|| std::is_x86_feature_detected!("bmi2"));
+
+#[cfg(all(feature = "pext", target_arch = "x86_64"))]
+fn bmi2() -> bool {
+    cfg!(target_feature = "bmi2") || *BMI2
+}
+
+pub fn rook_attacks(from: Square, occupied: Bitboard) -> Bitboard {
+    let magic = &ROOK_MAGICS[from as usize];
+    #[cfg(all(feature = "pext", target_arch = "x86_64"))]
+    if bmi2() {
+        // SAFETY: BMI2 support was checked
+        return ROOK_PEXT_ATTACKS[unsafe { magic.pext_index(occupied) }];
+    }
+    ROOK_ATTACKS[magic.index(occupied)]
+}
+
+pub fn bishop_attacks(from: Square, occupied: Bitboard) -> Bitboard {
+    let magic = &BISHOP_MAGICS[from as usize];
+    #[cfg(all(feature = "pext", target_arch = "x86_64"))]
+    if bmi2() {
+        // SAFETY: BMI2 support was checked
+        return BISHOP_PEXT_ATTACKS[unsafe { magic.pext_index(occupied) }];
+    }
+    BISHOP_ATTACKS[magic.index(occupied)]
+}
+
+/// Walks every direction from `square` until the edge of the board or the first blocker.
+/// With `mask` set, the last square before the edge is left out, since a blocker there can
+/// never change the attacks
+const fn slider_attacks(
+    square: Square,
+    occupied: Bitboard,
+    dirs: &[(Square, Square); 4],
+    mask: bool,
+) -> Bitboard {
+    let mut attacks = 0;
+    let mut dir = 0;
+    while dir < dirs.len() {
+        let (file_step, rank_step) = dirs[dir];
+        let mut file = square % 8 + file_step;
+        let mut rank = square / 8 + rank_step;
+        while 0 <= file && file < 8 && 0 <= rank && rank < 8 {
+            let next_file = file + file_step;
+            let next_rank = rank + rank_step;
+            if mask && !(0 <= next_file && next_file < 8 && 0 <= next_rank && next_rank < 8) {
+                break;
+            }
+            attacks |= 1 << (rank * 8 + file);
+            if occupied & 1 << (rank * 8 + file) > 0 {
+                break;
+            }
+            file = next_file;
+            rank = next_rank;
+        }
+        dir += 1;
+    }
+    attacks
+}
+
+const fn generate_magics(dirs: &[(Square, Square); 4], magic_numbers: &[u64; 64]) -> [Magic; 64] {
+    let mut magics = [Magic::EMPTY; 64];
+
+    let mut offset = 0;
+    let mut square = 0;
+    while square < 64 {
+        let mask = slider_attacks(square as Square, 0, dirs, true);
+        magics[square] = Magic {
+            mask,
+            magic: magic_numbers[square],
+            shift: 64 - mask.count_ones(),
+            offset,
+        };
+        offset += 1 << mask.count_ones();
+        square += 1;
+    }
+
+    magics
+}
+
+const fn generate_attacks<const N: usize>(
+    dirs: &[(Square, Square); 4],
+    magic_numbers: &[u64; 64],
+    pext: bool,
+) -> [Bitboard; N] {
+    let magics = generate_magics(dirs, magic_numbers);
+    let mut attacks = [0; N];
+
+    let mut square = 0;
+    while square < 64 {
+        let magic = magics[square];
+        // Enumerate every subset of the mask with the Carry-Rippler trick, which counts up in the
+        // same order as the `_pext_u64` indices
+        let mut occupied: Bitboard = 0;
+        let mut subset = 0;
+        loop {
+            let index = if pext {
+                magic.offset + subset
+            } else {
+                magic.index(occupied)
+            };
+            attacks[index] = slider_attacks(square as Square, occupied, dirs, false);
+            occupied = occupied.wrapping_sub(magic.mask) & magic.mask;
+            if occupied == 0 {
+                break;
+            }
+            subset += 1;
+        }
+        square += 1;
+    }
+
+    attacks
+}
+
+const ROOK_MAGIC_NUMBERS: [u64; 64] = [
+    0x1080004008801020,
+    0x0840092002C03000,
+    0x1900200010400900,
+    0x0880100008000480,
+    0x4200100420080200,
+    0x8100020100080400,
+    0x0200040110886200,
+    0x0200008040220411,
+    0x0404800084400220,
+    0x0000401000402000,
+    0x0086001081220440,
+    0x0408800800100280,
+    0x000A001201040820,
+    0x8848800200840080,
+    0x4001000100040200,
+    0x0442000102105084,
+    0x9080010020804100,
+    0x0040404000201009,
+    0x0000808010002009,
+    0x2200090021D00100,
+    0x0008008008040080,
+    0x0004004002010040,
+    0x0011040008015042,
+    0x00000A0001768104,
+    0x0000800080204009,
+    0x2010004140002001,
+    0x9800200280100080,
+    0x1000100080080080,
+    0x0442000A00049020,
+    0x2100040080020080,
+    0x0800120400900148,
+    0x0010040A00128541,
+    0x2800804000800030,
+    0x1010002000400041,
+    0x4000200011004100,
+    0x0610008410800800,
+    0x0400802402800800,
+    0xC100020080800400,
+    0x0002000802000401,
+    0x0182085882000401,
+    0x0220204000808000,
+    0x2860100040024022,
+    0x0001002004110040,
+    0x99101042000A0020,
+    0x0004080004008080,
+    0x0010040002008080,
+    0x2012004881020004,
+    0x8300842444820011,
+    0x0088403882010200,
+    0x0820400080210100,
+    0x0110910040A00300,
+    0x0801100280080480,
+    0x0242009008200600,
+    0x1002000489500200,
+    0x0040800200010080,
+    0x0091800041000080,
+    0x0000209300488001,
+    0x04C1002414824001,
+    0x020020000B001041,
+    0x7000100004200901,
+    0x8002002004100802,
+    0x30010002084C0007,
+    0x0888221800813004,
+    0x4000002840840112,
+];
+
+const BISHOP_MAGIC_NUMBERS: [u64; 64] = [
+    0xA010041108003100,
+    0x006082020A002900,
+    0x6810010619200000,
+    0x08281A0520000408,
+    0x0001104001000400,
+    0x0018901008048400,
+    0x00040A0210245280,
+    0x000200210808A402,
+    0x9140048410821200,
+    0x0800091010820041,
+    0x20504804832202C0,
+    0x0100091401081000,
+    0x8021011140000012,
+    0x0810020804450400,
+    0x208B0542109008A2,
+    0x0080084A08040204,
+    0x0040E2A80811244C,
+    0x2505022008008108,
+    0x0430220100420040,
+    0x010A040420220040,
+    0x1105000290400000,
+    0x0093001200822120,
+    0x4000A62048043004,
+    0x280120048A015004,
+    0x006090002A020814,
+    0x44042000240800D0,
+    0x01102800040A4400,
+    0x1004080080220040,
+    0x0001001011004024,
+    0x0010044000805040,
+    0x0914041200820100,
+    0x0004821012821480,
+    0x0024040500C05021,
+    0x0088611002080200,
+    0x0116080A00040020,
+    0x4000020080080080,
+    0x2450450140840040,
+    0x0000880201484100,
+    0x0222020404020092,
+    0x8081110600002E00,
+    0x2842101105000801,
+    0x1100809008001025,
+    0x00020202221C0400,
+    0x0422014022009020,
+    0x0210046102100C00,
+    0xC004008082029102,
+    0x00AA461801101200,
+    0x0404080080201108,
+    0x020542108C205002,
+    0x0410544804100100,
+    0x0040910841100000,
+    0x0400200042021100,
+    0x00004204850400C0,
+    0x0200100410A42102,
+    0x1040020801210102,
+    0x0805040410420000,
+    0x2884804130100200,
+    0x800C262201242000,
+    0x1058000194108800,
+    0x0014221054420204,
+    0x0104000012A02200,
+    0x0200881003300100,
+    0x0140400202840100,
+    0x0402020801010201,
+];
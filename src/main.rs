@@ -1,10 +1,4 @@
-mod board;
-mod r#move;
-mod move_generator;
-mod perft;
-mod search;
-mod search_test;
-mod uci;
+use perftmaster::{perft, search_test, uci};
 
 use clap::{Parser, Subcommand};
 
@@ -24,14 +18,41 @@ enum Command {
         #[arg(long)]
         fen: Option<String>,
 
+        /// Check against the D<n> node counts in an EPD file instead of the dataset
+        #[arg(long)]
+        epd: Option<String>,
+
         #[arg(long, short)]
         zobrist: bool,
+
+        /// Compare captures, quiets and evasions with the full move list instead of counting
+        #[arg(long, short, conflicts_with = "zobrist")]
+        staged: bool,
     },
     Search {
         depth: u8,
 
         #[arg(long)]
         fen: Option<String>,
+
+        /// Compare the evaluation of every position with its mirror instead of searching
+        #[arg(long, short)]
+        symmetry: bool,
+    },
+    /// Search every position of an EPD test suite and check the moves against bm and am
+    #[command(name = "epdtest")]
+    EpdTest {
+        path: String,
+
+        #[arg(long, required_unless_present_any = ["nodes", "time"])]
+        depth: Option<u8>,
+
+        #[arg(long)]
+        nodes: Option<u64>,
+
+        /// Milliseconds per position
+        #[arg(long)]
+        time: Option<u64>,
     },
 }
 
@@ -42,12 +63,28 @@ fn main() {
         Some(Command::Perft {
             depth,
             fen,
+            epd,
             zobrist,
-        }) => match zobrist {
-            false => perft::perft_test(depth, fen),
-            true => perft::zobrist_test(depth, fen),
+            staged,
+        }) => match (zobrist, staged) {
+            (true, _) => perft::zobrist_test(depth, fen),
+            (_, true) => perft::staged_test(depth, fen),
+            _ => perft::perft_test(depth, fen, epd),
         },
-        Some(Command::Search { depth, fen }) => search_test::search_test(depth, fen),
+        Some(Command::Search {
+            depth,
+            fen,
+            symmetry,
+        }) => match symmetry {
+            false => search_test::search_test(depth, fen),
+            true => search_test::symmetry_test(depth, fen),
+        },
+        Some(Command::EpdTest {
+            path,
+            depth,
+            nodes,
+            time,
+        }) => search_test::epd_test(&path, depth, nodes, time),
         None => uci::run(),
     }
 }
@@ -14,7 +14,11 @@ impl Default for Move {
 impl std::fmt::Display for Move {
     fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
         let from = self.from();
-        let to = self.to();
+        let to = if self.is_castle() {
+            self.castle_king_to()
+        } else {
+            self.to()
+        };
 
         let from_string = from.as_square();
         let to_string = to.as_square();
@@ -161,6 +165,26 @@ impl Move {
         }
     }
 
+    // Castling is encoded as the king capturing its own rook, both end up on the same files as in
+    // standard chess
+    pub fn castle_king_to(&self) -> Square {
+        let rank = self.from() / 8 * 8;
+        match self.castle() {
+            CastleKind::KingSide => rank + 6,
+            CastleKind::QueenSide => rank + 2,
+            CastleKind::None => unreachable!(),
+        }
+    }
+
+    pub fn castle_rook_to(&self) -> Square {
+        let rank = self.from() / 8 * 8;
+        match self.castle() {
+            CastleKind::KingSide => rank + 5,
+            CastleKind::QueenSide => rank + 3,
+            CastleKind::None => unreachable!(),
+        }
+    }
+
     pub fn reverse(&self) -> Move {
         Move(self.to() | (self.from() << 6) | (self.flags() << 12))
     }
@@ -183,22 +207,29 @@ impl Move {
                     UciPiece::Knight => PieceKind::Knight,
                     UciPiece::Bishop => PieceKind::Bishop,
                     UciPiece::Queen => PieceKind::Queen,
-                    _ => unreachable!(),
+                    // Left for `is_legal` to reject
+                    UciPiece::Pawn | UciPiece::King => PieceKind::None,
                 },
                 None => PieceKind::None,
             },
         )
     }
 
-    pub fn as_ucimove(&self) -> UciMove {
+    /// With UCI_Chess960 castling is sent as the king capturing its own rook
+    pub fn as_ucimove(&self, chess960: bool) -> UciMove {
+        let to = if self.is_castle() && !chess960 {
+            self.castle_king_to()
+        } else {
+            self.to()
+        };
         UciMove {
             from: UciSquare {
                 file: (b'a' + (self.from() % 8) as u8) as char,
                 rank: (self.from() / 8) as u8 + 1,
             },
             to: UciSquare {
-                file: (b'a' + (self.to() % 8) as u8) as char,
-                rank: (self.to() / 8) as u8 + 1,
+                file: (b'a' + (to % 8) as u8) as char,
+                rank: (to / 8) as u8 + 1,
             },
             promotion: match self.promotion() {
                 PieceKind::Rook => Some(UciPiece::Rook),
@@ -1,5 +1,6 @@
 use crate::{
-    board::{Bitboard, Board, Color, Square},
+    board::{Bitboard, Board, CastleKind, Color, PieceKind, Square},
+    magic::{bishop_attacks, rook_attacks},
     r#move::Move,
 };
 
@@ -53,6 +54,7 @@ const NOT_A_FILE: Bitboard = 0xFEFEFEFEFEFEFEFE;
 const NOT_AB_FILE: Bitboard = 0xFCFCFCFCFCFCFCFC;
 const NOT_GH_FILE: Bitboard = 0x3F3F3F3F3F3F3F3F;
 const NOT_H_FILE: Bitboard = 0x7F7F7F7F7F7F7F7F;
+const PROMOTION_RANKS: Bitboard = 0xFF000000000000FF;
 
 #[derive(Clone, Copy, Debug)]
 enum Dir {
@@ -228,14 +230,249 @@ fn get_negative_ray_attacks(square: Square, dir: Dir, occupied: Bitboard) -> Bit
     attacks
 }
 
+fn white_pawn_attacks(from: Square) -> Bitboard {
+    (Bitboard::checked_shl(1, (from + 7) as u32).unwrap_or(0) & NOT_H_FILE)
+        | (Bitboard::checked_shl(1, (from + 9) as u32).unwrap_or(0) & NOT_A_FILE)
+}
+
+fn black_pawn_attacks(from: Square) -> Bitboard {
+    (Bitboard::checked_shl(1, (from - 7) as u32).unwrap_or(0) & NOT_A_FILE)
+        | (Bitboard::checked_shl(1, (from - 9) as u32).unwrap_or(0) & NOT_H_FILE)
+}
+
+fn knight_attacks(from: Square) -> Bitboard {
+    (Bitboard::checked_shl(1, (from + 15) as u32).unwrap_or(0) & NOT_H_FILE)
+        | (Bitboard::checked_shl(1, (from + 17) as u32).unwrap_or(0) & NOT_A_FILE)
+        | (Bitboard::checked_shl(1, (from + 6) as u32).unwrap_or(0) & NOT_GH_FILE)
+        | (Bitboard::checked_shl(1, (from + 10) as u32).unwrap_or(0) & NOT_AB_FILE)
+        | (Bitboard::checked_shl(1, (from - 10) as u32).unwrap_or(0) & NOT_GH_FILE)
+        | (Bitboard::checked_shl(1, (from - 6) as u32).unwrap_or(0) & NOT_AB_FILE)
+        | (Bitboard::checked_shl(1, (from - 17) as u32).unwrap_or(0) & NOT_H_FILE)
+        | (Bitboard::checked_shl(1, (from - 15) as u32).unwrap_or(0) & NOT_A_FILE)
+}
+
+fn queen_attacks(from: Square, occupied: Bitboard) -> Bitboard {
+    rook_attacks(from, occupied) | bishop_attacks(from, occupied)
+}
+
+fn king_attacks(from: Square) -> Bitboard {
+    let mut bitboard = (Bitboard::checked_shl(1, (from - 1) as u32).unwrap_or(0) & NOT_H_FILE)
+        | Bitboard::checked_shl(1, from as u32).unwrap_or(0)
+        | (Bitboard::checked_shl(1, (from + 1) as u32).unwrap_or(0) & NOT_A_FILE);
+    bitboard |= bitboard.checked_shl(8_u32).unwrap_or(0);
+    bitboard |= bitboard.checked_shr(8_u32).unwrap_or(0);
+    bitboard
+}
+
 impl Board {
     pub fn generate_moves(&mut self) -> MoveGeneratorResult {
-        MoveGenerator::generate_moves(self)
+        MoveGenerator::new(self, Stage::All).generate()
+    }
+
+    /// Captures, en passant and every promotion, quiet or not
+    pub fn generate_captures(&mut self) -> MoveGeneratorResult {
+        MoveGenerator::new(self, Stage::Captures).generate()
+    }
+
+    /// Every legal move `generate_captures` leaves out, castling included
+    pub fn generate_quiets(&mut self) -> MoveGeneratorResult {
+        MoveGenerator::new(self, Stage::Quiets).generate()
+    }
+
+    /// All legal moves out of check, only valid while the side to move is in check
+    pub fn generate_evasions(&mut self) -> MoveGeneratorResult {
+        let move_generator = MoveGenerator::new(self, Stage::Evasions);
+        debug_assert!(move_generator.checkers > 0, "Not in check");
+        move_generator.generate_evasions()
+    }
+
+    /// Whether the side to move could make `m` if the safety of its own king is ignored. Cheap
+    /// enough to throw out moves from hash collisions before they reach `is_legal` or `make_move`
+    pub fn is_pseudo_legal(&self, m: Move) -> bool {
+        // Promotions set the sign bit, so `flags` comes back sign extended
+        let flags = m.flags() & 0b1111;
+        if m == Move::NULL || flags & 0b1110 == 0b0110 {
+            return false;
+        }
+        let (from, to) = (m.from(), m.to());
+        let piece = self.get_piece(from);
+        if piece.color != self.turn {
+            return false;
+        }
+
+        let own = self.own_pieces();
+        let opponent = self.opponent_pieces();
+        let occupied = own | opponent;
+
+        if m.is_castle() {
+            let king_side = m.castle() == CastleKind::KingSide;
+            let castling_right = match (self.turn, king_side) {
+                (Color::White, true) => 0b1000,
+                (Color::White, false) => 0b0100,
+                (Color::Black, true) => 0b0010,
+                (Color::Black, false) => 0b0001,
+                (Color::None, _) => unreachable!(),
+            };
+            return piece.kind == PieceKind::King
+                && self.castling_rights & castling_right > 0
+                && self.castling_rook(self.turn, king_side) == to;
+        }
+        if 1 << to & own > 0 {
+            return false;
+        }
+
+        let pawn_attacks = match self.turn {
+            Color::White => white_pawn_attacks(from),
+            Color::Black => black_pawn_attacks(from),
+            Color::None => unreachable!(),
+        };
+        if m.is_en_passant() {
+            return piece.kind == PieceKind::Pawn && to == self.ep && pawn_attacks & 1 << to > 0;
+        }
+        // The capture flag has to agree with what is on the target square
+        if m.is_capture() != (1 << to & opponent > 0) {
+            return false;
+        }
+
+        let attacks = match piece.kind {
+            PieceKind::Pawn => pawn_attacks,
+            PieceKind::Rook => rook_attacks(from, occupied),
+            PieceKind::Knight => knight_attacks(from),
+            PieceKind::Bishop => bishop_attacks(from, occupied),
+            PieceKind::Queen => queen_attacks(from, occupied),
+            PieceKind::King => king_attacks(from),
+            PieceKind::None => unreachable!(),
+        };
+        if piece.kind != PieceKind::Pawn {
+            return flags & !0b0100 == 0 && attacks & 1 << to > 0;
+        }
+
+        if m.is_promotion() != (to / 8 == 0 || to / 8 == 7) {
+            return false;
+        }
+        let forward = from + self.turn;
+        if m.is_double_push() {
+            let start_rank = match self.turn {
+                Color::White => 1,
+                Color::Black => 6,
+                Color::None => unreachable!(),
+            };
+            return from / 8 == start_rank
+                && to == forward + self.turn
+                && (1 << forward | 1 << to) & occupied == 0;
+        }
+        if m.is_capture() {
+            attacks & 1 << to > 0
+        } else {
+            to == forward && 1 << to & occupied == 0
+        }
+    }
+
+    /// Whether `m` is one of the moves `generate_moves` returns, only the moved piece has its
+    /// moves generated
+    pub fn is_legal(&mut self, m: Move) -> bool {
+        if !self.is_pseudo_legal(m) {
+            return false;
+        }
+        let mut move_generator = MoveGenerator::new(self, Stage::All);
+        move_generator.sources = 1 << m.from();
+        move_generator.generate().any(|legal| legal == m)
+    }
+
+    /// Whether the legal move `m` checks the opponent, without making it
+    pub fn gives_check(&self, m: Move) -> bool {
+        let own = self.own_pieces();
+        let opponent = self.opponent_pieces();
+        let king = (self.kings & opponent)
+            .bitscan_forward()
+            .expect("No king found");
+        let from = m.from();
+
+        // The occupancy after the move, the square and kind of the piece that can check directly
+        // and the squares the moved pieces left
+        let mut occupied = own | opponent;
+        let (to, kind, vacated): (Square, PieceKind, Bitboard) = if m.is_castle() {
+            occupied ^= 1 << from | 1 << m.to();
+            occupied |= 1 << m.castle_king_to() | 1 << m.castle_rook_to();
+            (m.castle_rook_to(), PieceKind::Rook, 1 << from | 1 << m.to())
+        } else {
+            occupied ^= 1 << from;
+            occupied |= 1 << m.to();
+            if m.is_en_passant() {
+                occupied ^= 1 << (m.to() - self.turn);
+            }
+            let kind = match m.promotion() {
+                PieceKind::None => self.get_piece(from).kind,
+                promotion => promotion,
+            };
+            (m.to(), kind, 1 << from)
+        };
+
+        let direct = match kind {
+            PieceKind::Pawn => match self.turn {
+                Color::White => white_pawn_attacks(to),
+                Color::Black => black_pawn_attacks(to),
+                Color::None => unreachable!(),
+            },
+            PieceKind::Rook => rook_attacks(to, occupied),
+            PieceKind::Knight => knight_attacks(to),
+            PieceKind::Bishop => bishop_attacks(to, occupied),
+            PieceKind::Queen => queen_attacks(to, occupied),
+            PieceKind::King | PieceKind::None => 0,
+        };
+        if direct & 1 << king > 0 {
+            return true;
+        }
+
+        // Discovered checks, from sliders on a line to the king that the move cleared
+        let mut snipers = ((rook_attacks(king, 0) & (self.rooks | self.queens))
+            | (bishop_attacks(king, 0) & (self.bishops | self.queens)))
+            & own
+            & !vacated;
+        while let Some(sniper) = snipers.pop_lsb() {
+            if IN_BETWEEN_RAYS[sniper as usize][king as usize] & occupied == 0 {
+                return true;
+            }
+        }
+        false
+    }
+
+    /// All pieces of `color` attacking `square`, given the occupancy `occupied`
+    pub fn attackers(&self, square: Square, color: Color, occupied: Bitboard) -> Bitboard {
+        let pieces = match color {
+            Color::White => self.white_pieces,
+            Color::Black => self.black_pieces,
+            Color::None => unreachable!(),
+        };
+
+        // A pawn of `color` attacks `square` if a pawn of the other color on `square` would
+        // attack it back
+        (match color {
+            Color::White => black_pawn_attacks(square) & self.pawns,
+            Color::Black => white_pawn_attacks(square) & self.pawns,
+            Color::None => unreachable!(),
+        } | (rook_attacks(square, occupied) & (self.rooks | self.queens))
+            | (knight_attacks(square) & self.knights)
+            | (bishop_attacks(square, occupied) & (self.bishops | self.queens))
+            | (king_attacks(square) & self.kings))
+            & pieces
     }
 }
 
+/// Which moves a `MoveGenerator` produces, all of them share the pin and check setup
+#[derive(Clone, Copy, PartialEq, Eq)]
+enum Stage {
+    All,
+    Captures,
+    Quiets,
+    Evasions,
+}
+
 struct MoveGenerator<'a> {
     board: &'a mut Board,
+    stage: Stage,
+    /// The pieces to generate moves for
+    sources: Bitboard,
 
     attacks: Bitboard,
     checkers: Bitboard,
@@ -319,9 +556,11 @@ impl Iterator for MoveGeneratorResult {
 }
 
 impl<'a> MoveGenerator<'a> {
-    fn generate_moves(board: &mut Board) -> MoveGeneratorResult {
+    fn new(board: &'a mut Board, stage: Stage) -> Self {
         let mut move_generator = MoveGenerator {
             board,
+            stage,
+            sources: Bitboard::MAX,
 
             attacks: 0,
             checkers: 0,
@@ -334,27 +573,84 @@ impl<'a> MoveGenerator<'a> {
         move_generator.get_block_ray();
         move_generator.get_pinned();
 
+        move_generator
+    }
+
+    fn generate(mut self) -> MoveGeneratorResult {
+        let mut moves = MoveGeneratorResult {
+            moves: [Move::NULL; 218],
+            in_check: self.checkers > 0,
+
+            len: 0,
+            index: 0,
+        };
+
+        if self.checkers.count_ones() != 2 {
+            self.generate_pawn_moves(&mut moves);
+            self.generate_rook_moves(&mut moves);
+            self.generate_knight_moves(&mut moves);
+            self.generate_bishop_moves(&mut moves);
+            self.generate_queen_moves(&mut moves);
+        }
+
+        self.generate_king_moves(&mut moves);
+
+        moves
+    }
+
+    /// King moves come first, on a double check they are the only evasions. Otherwise the other
+    /// pieces can only capture the checker or block its ray, which pinned pieces never can
+    fn generate_evasions(mut self) -> MoveGeneratorResult {
         let mut moves = MoveGeneratorResult {
             moves: [Move::NULL; 218],
-            in_check: move_generator.checkers > 0,
+            in_check: true,
 
             len: 0,
             index: 0,
         };
 
-        if move_generator.checkers.count_ones() != 2 {
-            move_generator.generate_pawn_moves(&mut moves);
-            move_generator.generate_rook_moves(&mut moves);
-            move_generator.generate_knight_moves(&mut moves);
-            move_generator.generate_bishop_moves(&mut moves);
-            move_generator.generate_queen_moves(&mut moves);
+        self.generate_king_moves(&mut moves);
+        if self.checkers.count_ones() == 2 {
+            return moves;
         }
 
-        move_generator.generate_king_moves(&mut moves);
+        self.sources &= !self.pinned;
+        self.generate_pawn_moves(&mut moves);
+        self.generate_rook_moves(&mut moves);
+        self.generate_knight_moves(&mut moves);
+        self.generate_bishop_moves(&mut moves);
+        self.generate_queen_moves(&mut moves);
 
         moves
     }
 
+    /// The squares pieces other than pawns may move to in the current stage
+    fn targets(&self) -> Bitboard {
+        let opponent = self.board.opponent_pieces();
+        match self.stage {
+            Stage::All => !self.board.own_pieces(),
+            Stage::Captures => opponent,
+            Stage::Evasions => self.block_ray & !self.board.own_pieces(),
+            Stage::Quiets => !(self.board.own_pieces() | opponent),
+        }
+    }
+
+    /// Like `targets`, but promotions and en passant count as captures
+    fn pawn_targets(&self) -> Bitboard {
+        let noisy = self.board.opponent_pieces()
+            | PROMOTION_RANKS
+            | if self.board.ep != -1 {
+                1 << self.board.ep
+            } else {
+                0
+            };
+        match self.stage {
+            Stage::All | Stage::Evasions => Bitboard::MAX,
+            Stage::Captures => noisy,
+            Stage::Quiets => !noisy,
+        }
+    }
+
     fn get_attacks(&mut self) {
         let own = self.board.own_pieces();
         let opponent = self.board.opponent_pieces();
@@ -363,30 +659,30 @@ impl<'a> MoveGenerator<'a> {
         let mut pawns = self.board.pawns & opponent;
         while let Some(from) = pawns.pop_lsb() {
             self.attacks |= match self.board.turn {
-                Color::White => self.black_pawn_attacks(from),
-                Color::Black => self.white_pawn_attacks(from),
+                Color::White => black_pawn_attacks(from),
+                Color::Black => white_pawn_attacks(from),
                 Color::None => unreachable!(),
             };
         }
         let mut rooks = self.board.rooks & opponent;
         while let Some(from) = rooks.pop_lsb() {
-            self.attacks |= self.rook_attacks(from, occupied);
+            self.attacks |= rook_attacks(from, occupied);
         }
         let mut knights = self.board.knights & opponent;
         while let Some(from) = knights.pop_lsb() {
-            self.attacks |= self.knight_attacks(from);
+            self.attacks |= knight_attacks(from);
         }
         let mut bishops = self.board.bishops & opponent;
         while let Some(from) = bishops.pop_lsb() {
-            self.attacks |= self.bishop_attacks(from, occupied);
+            self.attacks |= bishop_attacks(from, occupied);
         }
         let mut queens = self.board.queens & opponent;
         while let Some(from) = queens.pop_lsb() {
-            self.attacks |= self.queen_attacks(from, occupied);
+            self.attacks |= queen_attacks(from, occupied);
         }
         let mut kings = self.board.kings & opponent;
         while let Some(from) = kings.pop_lsb() {
-            self.attacks |= self.king_attacks(from);
+            self.attacks |= king_attacks(from);
         }
     }
 
@@ -400,14 +696,14 @@ impl<'a> MoveGenerator<'a> {
         let from = king.pop_lsb().expect("No king found");
 
         self.checkers = (match self.board.turn {
-            Color::White => self.white_pawn_attacks(from) & self.board.pawns,
-            Color::Black => self.black_pawn_attacks(from) & self.board.pawns,
+            Color::White => white_pawn_attacks(from) & self.board.pawns,
+            Color::Black => black_pawn_attacks(from) & self.board.pawns,
             Color::None => unreachable!(),
-        } | (self.rook_attacks(from, occupied) & self.board.rooks)
-            | (self.knight_attacks(from) & self.board.knights)
-            | (self.bishop_attacks(from, occupied) & self.board.bishops)
-            | (self.queen_attacks(from, occupied) & self.board.queens)
-            | (self.king_attacks(from) & self.board.kings))
+        } | (rook_attacks(from, occupied) & self.board.rooks)
+            | (knight_attacks(from) & self.board.knights)
+            | (bishop_attacks(from, occupied) & self.board.bishops)
+            | (queen_attacks(from, occupied) & self.board.queens)
+            | (king_attacks(from) & self.board.kings))
             & opponent
     }
 
@@ -429,24 +725,16 @@ impl<'a> MoveGenerator<'a> {
         let king = self.board.kings & own;
         let king_square = king.bitscan_forward().expect("No king found");
 
-        let mut pinner = self.xray(
-            |mg: &mut MoveGenerator<'_>, from: Square, occupied: Bitboard| {
-                mg.rook_attacks(from, occupied)
-            },
-            king_square,
-        ) & (self.board.rooks | self.board.queens)
+        let mut pinner = self.xray(rook_attacks, king_square)
+            & (self.board.rooks | self.board.queens)
             & opponent;
 
         while let Some(pinner_square) = pinner.pop_lsb() {
             self.pinned |= IN_BETWEEN_RAYS[pinner_square as usize][king_square as usize] & own;
         }
 
-        pinner = self.xray(
-            |mg: &mut MoveGenerator<'_>, from: Square, occupied: Bitboard| {
-                mg.bishop_attacks(from, occupied)
-            },
-            king_square,
-        ) & (self.board.bishops | self.board.queens)
+        pinner = self.xray(bishop_attacks, king_square)
+            & (self.board.bishops | self.board.queens)
             & opponent;
 
         while let Some(pinner_square) = pinner.pop_lsb() {
@@ -454,15 +742,12 @@ impl<'a> MoveGenerator<'a> {
         }
     }
 
-    fn xray<T>(&mut self, attack_fn: T, from: Square) -> Bitboard
-    where
-        T: Fn(&mut MoveGenerator, Square, Bitboard) -> Bitboard,
-    {
+    fn xray(&self, attack_fn: fn(Square, Bitboard) -> Bitboard, from: Square) -> Bitboard {
         let occupied = self.board.own_pieces() | self.board.opponent_pieces();
         let mut blockers = self.board.own_pieces();
-        let attacks = attack_fn(self, from, occupied);
+        let attacks = attack_fn(from, occupied);
         blockers &= attacks;
-        attacks ^ attack_fn(self, from, occupied ^ blockers)
+        attacks ^ attack_fn(from, occupied ^ blockers)
     }
 
     fn xray_dir(&mut self, from: Square, to: Square) -> Bitboard {
@@ -488,7 +773,7 @@ impl<'a> MoveGenerator<'a> {
     }
 
     fn generate_white_pawn_moves(&mut self, moves: &mut MoveGeneratorResult) {
-        let mut pawns = self.board.pawns & self.board.white_pieces;
+        let mut pawns = self.board.pawns & self.board.white_pieces & self.sources;
         let blockers = self.board.white_pieces | self.board.black_pieces;
         let free = !blockers;
         while let Some(from) = pawns.pop_lsb() {
@@ -525,6 +810,7 @@ impl<'a> MoveGenerator<'a> {
                     from,
                 );
             }
+            bitboard &= self.pawn_targets();
 
             while let Some(to) = bitboard.pop_lsb() {
                 let flags = if 1 << to & self.board.black_pieces > 0 {
@@ -604,13 +890,8 @@ impl<'a> MoveGenerator<'a> {
         }
     }
 
-    fn white_pawn_attacks(&self, from: Square) -> Bitboard {
-        (Bitboard::checked_shl(1, (from + 7) as u32).unwrap_or(0) & NOT_H_FILE)
-            | (Bitboard::checked_shl(1, (from + 9) as u32).unwrap_or(0) & NOT_A_FILE)
-    }
-
     fn generate_black_pawn_moves(&mut self, moves: &mut MoveGeneratorResult) {
-        let mut pawns = self.board.pawns & self.board.black_pieces;
+        let mut pawns = self.board.pawns & self.board.black_pieces & self.sources;
         let blockers = self.board.white_pieces | self.board.black_pieces;
         let free = !blockers;
         while let Some(from) = pawns.pop_lsb() {
@@ -647,6 +928,7 @@ impl<'a> MoveGenerator<'a> {
                     from,
                 );
             }
+            bitboard &= self.pawn_targets();
 
             while let Some(to) = bitboard.pop_lsb() {
                 let flags = if 1 << to & self.board.white_pieces > 0 {
@@ -726,22 +1008,17 @@ impl<'a> MoveGenerator<'a> {
         }
     }
 
-    fn black_pawn_attacks(&self, from: Square) -> Bitboard {
-        (Bitboard::checked_shl(1, (from - 7) as u32).unwrap_or(0) & NOT_A_FILE)
-            | (Bitboard::checked_shl(1, (from - 9) as u32).unwrap_or(0) & NOT_H_FILE)
-    }
-
     fn generate_rook_moves(&mut self, moves: &mut MoveGeneratorResult) {
         let own = self.board.own_pieces();
         let opponent = self.board.opponent_pieces();
 
         let occupied = own | opponent;
 
-        let free = !own;
+        let free = self.targets();
 
-        let mut rooks = self.board.rooks & own;
+        let mut rooks = self.board.rooks & own & self.sources;
         while let Some(from) = rooks.pop_lsb() {
-            let mut bitboard = self.rook_attacks(from, occupied) & self.block_ray & free;
+            let mut bitboard = rook_attacks(from, occupied) & self.block_ray & free;
 
             if 1 << from & self.pinned > 0 {
                 bitboard &= self.xray_dir(
@@ -759,23 +1036,16 @@ impl<'a> MoveGenerator<'a> {
         }
     }
 
-    fn rook_attacks(&self, from: Square, occupied: Bitboard) -> Bitboard {
-        get_positive_ray_attacks(from, Dir::North, occupied)
-            | get_positive_ray_attacks(from, Dir::East, occupied)
-            | get_negative_ray_attacks(from, Dir::West, occupied)
-            | get_negative_ray_attacks(from, Dir::South, occupied)
-    }
-
     fn generate_knight_moves(&mut self, moves: &mut MoveGeneratorResult) {
         let own = self.board.own_pieces();
         let opponent = self.board.opponent_pieces();
 
-        let free = !own;
+        let free = self.targets();
 
-        let mut knights = self.board.knights & own & !self.pinned;
+        let mut knights = self.board.knights & own & self.sources & !self.pinned;
 
         while let Some(from) = knights.pop_lsb() {
-            let mut bitboard = self.knight_attacks(from) & self.block_ray & free;
+            let mut bitboard = knight_attacks(from) & self.block_ray & free;
 
             while let Some(to) = bitboard.pop_lsb() {
                 let flags = if 1 << to & opponent > 0 { 0b0100 } else { 0 };
@@ -784,28 +1054,17 @@ impl<'a> MoveGenerator<'a> {
         }
     }
 
-    fn knight_attacks(&self, from: Square) -> Bitboard {
-        (Bitboard::checked_shl(1, (from + 15) as u32).unwrap_or(0) & NOT_H_FILE)
-            | (Bitboard::checked_shl(1, (from + 17) as u32).unwrap_or(0) & NOT_A_FILE)
-            | (Bitboard::checked_shl(1, (from + 6) as u32).unwrap_or(0) & NOT_GH_FILE)
-            | (Bitboard::checked_shl(1, (from + 10) as u32).unwrap_or(0) & NOT_AB_FILE)
-            | (Bitboard::checked_shl(1, (from - 10) as u32).unwrap_or(0) & NOT_GH_FILE)
-            | (Bitboard::checked_shl(1, (from - 6) as u32).unwrap_or(0) & NOT_AB_FILE)
-            | (Bitboard::checked_shl(1, (from - 17) as u32).unwrap_or(0) & NOT_H_FILE)
-            | (Bitboard::checked_shl(1, (from - 15) as u32).unwrap_or(0) & NOT_A_FILE)
-    }
-
     fn generate_bishop_moves(&mut self, moves: &mut MoveGeneratorResult) {
         let own = self.board.own_pieces();
         let opponent = self.board.opponent_pieces();
 
         let occupied = own | opponent;
 
-        let free = !own;
+        let free = self.targets();
 
-        let mut bishops = self.board.bishops & own;
+        let mut bishops = self.board.bishops & own & self.sources;
         while let Some(from) = bishops.pop_lsb() {
-            let mut bitboard = self.bishop_attacks(from, occupied) & self.block_ray & free;
+            let mut bitboard = bishop_attacks(from, occupied) & self.block_ray & free;
             if 1 << from & self.pinned > 0 {
                 bitboard &= self.xray_dir(
                     (self.board.kings & own)
@@ -822,24 +1081,17 @@ impl<'a> MoveGenerator<'a> {
         }
     }
 
-    fn bishop_attacks(&self, from: Square, occupied: Bitboard) -> Bitboard {
-        get_positive_ray_attacks(from, Dir::NorthWest, occupied)
-            | get_positive_ray_attacks(from, Dir::NorthEast, occupied)
-            | get_negative_ray_attacks(from, Dir::SouthEast, occupied)
-            | get_negative_ray_attacks(from, Dir::SouthWest, occupied)
-    }
-
     fn generate_queen_moves(&mut self, moves: &mut MoveGeneratorResult) {
         let own = self.board.own_pieces();
         let opponent = self.board.opponent_pieces();
 
         let occupied = own | opponent;
 
-        let free = !own;
+        let free = self.targets();
 
-        let mut queens = self.board.queens & own;
+        let mut queens = self.board.queens & own & self.sources;
         while let Some(from) = queens.pop_lsb() {
-            let mut bitboard = self.queen_attacks(from, occupied) & self.block_ray & free;
+            let mut bitboard = queen_attacks(from, occupied) & self.block_ray & free;
 
             if 1 << from & self.pinned > 0 {
                 bitboard &= self.xray_dir(
@@ -857,30 +1109,27 @@ impl<'a> MoveGenerator<'a> {
         }
     }
 
-    fn queen_attacks(&self, from: Square, occupied: Bitboard) -> Bitboard {
-        get_positive_ray_attacks(from, Dir::NorthWest, occupied)
-            | get_positive_ray_attacks(from, Dir::North, occupied)
-            | get_positive_ray_attacks(from, Dir::NorthEast, occupied)
-            | get_positive_ray_attacks(from, Dir::East, occupied)
-            | get_negative_ray_attacks(from, Dir::SouthEast, occupied)
-            | get_negative_ray_attacks(from, Dir::South, occupied)
-            | get_negative_ray_attacks(from, Dir::SouthWest, occupied)
-            | get_negative_ray_attacks(from, Dir::West, occupied)
-    }
-
     fn generate_king_moves(&mut self, moves: &mut MoveGeneratorResult) {
         let own = self.board.own_pieces();
         let opponent = self.board.opponent_pieces();
 
         let occupied = own | opponent;
 
-        let free = !own;
+        // The king gets out of check by moving, not by capturing or blocking on the check ray
+        let free = match self.stage {
+            Stage::Evasions => !own,
+            _ => self.targets(),
+        };
 
         let mut king = self.board.kings & own;
         let from = king.pop_lsb().expect("No king found");
+        if 1 << from & self.sources == 0 {
+            return;
+        }
 
-        let mut bitboard = self.king_attacks(from) & free & !self.attacks;
-        while let Some(checker_square) = self.checkers.pop_lsb() {
+        let mut bitboard = king_attacks(from) & free & !self.attacks;
+        let mut checkers = self.checkers;
+        while let Some(checker_square) = checkers.pop_lsb() {
             if 1 << checker_square & self.board.pawns == 0
                 && let Some(dir) = Dir::from_squares(checker_square, from)
             {
@@ -893,46 +1142,42 @@ impl<'a> MoveGenerator<'a> {
             moves.push(Move::new(from, to, flags));
         }
 
-        // Castling
-        match self.board.turn {
-            Color::White => {
-                if self.board.castling_rights & 0b1000 > 0
-                    && occupied & 0b01100000 == 0
-                    && !self.attacks & 0b01110000 == 0b01110000
-                {
-                    moves.push(Move::new(from, from + 2, 0b0010));
-                }
-                if self.board.castling_rights & 0b0100 > 0
-                    && occupied & 0b00001110 == 0
-                    && !self.attacks & 0b00011100 == 0b00011100
-                {
-                    moves.push(Move::new(from, from - 2, 0b0011));
-                }
+        if matches!(self.stage, Stage::Captures | Stage::Evasions) {
+            return;
+        }
+
+        // Castling, encoded as the king capturing its own rook
+        let castling_rights = match self.board.turn {
+            Color::White => [(0b1000, true), (0b0100, false)],
+            Color::Black => [(0b0010, true), (0b0001, false)],
+            Color::None => unreachable!(),
+        };
+        for (castling_right, king_side) in castling_rights {
+            if self.board.castling_rights & castling_right == 0 {
+                continue;
             }
-            Color::Black => {
-                if self.board.castling_rights & 0b0010 > 0
-                    && occupied & (0b01100000 << 56) == 0
-                    && !self.attacks & (0b01110000 << 56) == 0b01110000 << 56
-                {
-                    moves.push(Move::new(from, from + 2, 0b0010));
-                }
-                if self.board.castling_rights & 0b0001 > 0
-                    && occupied & (0b00001110 << 56) == 0
-                    && !self.attacks & (0b00011100 << 56) == 0b00011100 << 56
-                {
-                    moves.push(Move::new(from, from - 2, 0b0011));
-                }
+            let rook = self.board.castling_rook(self.board.turn, king_side);
+            let m = Move::new(from, rook, if king_side { 0b0010 } else { 0b0011 });
+            let king_to = m.castle_king_to();
+            let rook_to = m.castle_rook_to();
+
+            // Everything the king and rook pass over has to be empty, apart from themselves
+            let castlers = 1 << from | 1 << rook;
+            let king_path = IN_BETWEEN_RAYS[from as usize][king_to as usize] | 1 << king_to;
+            let rook_path = IN_BETWEEN_RAYS[rook as usize][rook_to as usize] | 1 << rook_to;
+            if (king_path | rook_path) & occupied & !castlers > 0
+                || (king_path | 1 << from) & self.attacks > 0
+            {
+                continue;
+            }
+
+            // In Chess960 the rook itself may be blocking an attack on the king's destination
+            let sliders = (self.board.rooks | self.board.queens) & opponent;
+            if rook_attacks(king_to, occupied ^ castlers) & sliders > 0 {
+                continue;
             }
-            Color::None => unreachable!(),
-        }
-    }
 
-    fn king_attacks(&self, from: Square) -> Bitboard {
-        let mut bitboard = (Bitboard::checked_shl(1, (from - 1) as u32).unwrap_or(0) & NOT_H_FILE)
-            | Bitboard::checked_shl(1, from as u32).unwrap_or(0)
-            | (Bitboard::checked_shl(1, (from + 1) as u32).unwrap_or(0) & NOT_A_FILE);
-        bitboard |= bitboard.checked_shl(8_u32).unwrap_or(0);
-        bitboard |= bitboard.checked_shr(8_u32).unwrap_or(0);
-        bitboard
+            moves.push(m);
+        }
     }
 }
@@ -0,0 +1,119 @@
+use crate::board::{Bitboard, Board, Color};
+
+const LIGHT_SQUARES: Bitboard = 0x55aa55aa55aa55aa;
+const DARK_SQUARES: Bitboard = 0xaa55aa55aa55aa55;
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum DrawReason {
+    FiftyMove,
+    ThreefoldRepetition,
+    InsufficientMaterial,
+}
+
+impl std::fmt::Display for DrawReason {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        match self {
+            DrawReason::FiftyMove => write!(f, "fifty-move rule"),
+            DrawReason::ThreefoldRepetition => write!(f, "threefold repetition"),
+            DrawReason::InsufficientMaterial => write!(f, "insufficient material"),
+        }
+    }
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Outcome {
+    Checkmate { winner: Color },
+    Stalemate,
+    Draw(DrawReason),
+}
+
+impl std::fmt::Display for Outcome {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        match self {
+            Outcome::Checkmate {
+                winner: Color::White,
+            } => write!(f, "white wins by checkmate"),
+            Outcome::Checkmate {
+                winner: Color::Black,
+            } => write!(f, "black wins by checkmate"),
+            Outcome::Checkmate { winner: Color::None } => unreachable!(),
+            Outcome::Stalemate => write!(f, "draw by stalemate"),
+            Outcome::Draw(reason) => write!(f, "draw by {reason}"),
+        }
+    }
+}
+
+impl Outcome {
+    /// The PGN game termination marker
+    pub fn result(&self) -> &'static str {
+        match self {
+            Outcome::Checkmate {
+                winner: Color::White,
+            } => "1-0",
+            Outcome::Checkmate {
+                winner: Color::Black,
+            } => "0-1",
+            Outcome::Checkmate { winner: Color::None } => unreachable!(),
+            Outcome::Stalemate | Outcome::Draw(_) => "1/2-1/2",
+        }
+    }
+}
+
+impl Board {
+    /// Whether the game is over, and how. Checkmate and stalemate take precedence over the draw
+    /// rules
+    pub fn outcome(&mut self) -> Option<Outcome> {
+        let moves = self.generate_moves();
+        if moves.is_empty() {
+            if moves.in_check {
+                let winner = match self.turn {
+                    Color::White => Color::Black,
+                    Color::Black => Color::White,
+                    Color::None => unreachable!(),
+                };
+                return Some(Outcome::Checkmate { winner });
+            }
+            return Some(Outcome::Stalemate);
+        }
+
+        self.draw_reason().map(Outcome::Draw)
+    }
+
+    /// NOTE: A checkmate delivered on the hundredth half move still wins, so the moves are
+    /// generated once the clock runs out
+    pub fn is_fifty_move_draw(&mut self) -> bool {
+        if self.half_move_clock < 100 {
+            return false;
+        }
+        let moves = self.generate_moves();
+        !(moves.in_check && moves.is_empty())
+    }
+
+    /// K vs K, KB vs K, KN vs K and any number of bishops that are all on the same color
+    pub fn is_insufficient_material(&self) -> bool {
+        if self.pawns | self.rooks | self.queens > 0 {
+            return false;
+        }
+
+        match (self.knights | self.bishops).count_ones() {
+            0 | 1 => true,
+            _ => {
+                self.knights == 0
+                    && (self.bishops & LIGHT_SQUARES == 0 || self.bishops & DARK_SQUARES == 0)
+            }
+        }
+    }
+
+    /// Draws by rule, does not check for stalemate
+    pub fn draw_reason(&mut self) -> Option<DrawReason> {
+        if self.is_fifty_move_draw() {
+            Some(DrawReason::FiftyMove)
+        } else if self.is_threefold() {
+            Some(DrawReason::ThreefoldRepetition)
+        } else if self.is_insufficient_material() {
+            Some(DrawReason::InsufficientMaterial)
+        } else {
+            None
+        }
+    }
+}
@@ -0,0 +1,263 @@
+use std::{
+    fs::File,
+    io::{self, BufReader, BufWriter, Read, Write},
+    path::Path,
+};
+
+use crate::{
+    board::{Board, Color, Piece, PieceKind, Square},
+    validate::PositionError,
+};
+
+pub const PACKED_SIZE: usize = 32;
+
+// Identifies the file container, the last byte is the format version
+const MAGIC: [u8; 4] = *b"PMP\x01";
+
+// Nibble for a rook that still has its castling right, which also gives the Chess960 rook files
+const CASTLING_ROOK: u8 = 6;
+const NO_EN_PASSANT: u8 = 0xf;
+
+#[derive(Debug)]
+pub enum PackedError {
+    Io(io::Error),
+    InvalidMagic,
+    Truncated,
+    TooManyPieces,
+    InvalidPiece(u8),
+    InvalidCastling,
+    InvalidEnPassant,
+    IllegalPosition(PositionError),
+}
+
+impl std::fmt::Display for PackedError {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        match self {
+            PackedError::Io(err) => write!(f, "{err}"),
+            PackedError::InvalidMagic => write!(f, "not a packed position file"),
+            PackedError::Truncated => write!(f, "file ends in the middle of a position"),
+            PackedError::TooManyPieces => write!(f, "more than 32 pieces"),
+            PackedError::InvalidPiece(code) => write!(f, "invalid piece code {code}"),
+            PackedError::InvalidCastling => write!(f, "castling rook without a matching king"),
+            PackedError::InvalidEnPassant => write!(f, "invalid en passant file"),
+            PackedError::IllegalPosition(err) => write!(f, "illegal position: {err}"),
+        }
+    }
+}
+
+impl std::error::Error for PackedError {}
+
+fn kind_from_code(code: u8) -> Option<PieceKind> {
+    match code {
+        0 => Some(PieceKind::Pawn),
+        1 | CASTLING_ROOK => Some(PieceKind::Rook),
+        2 => Some(PieceKind::Knight),
+        3 => Some(PieceKind::Bishop),
+        4 => Some(PieceKind::Queen),
+        5 => Some(PieceKind::King),
+        _ => None,
+    }
+}
+
+impl Board {
+    /// occupancy: u64 | a 4 bit piece for each occupied square from a1 up: u8[16] |
+    /// black to move, Chess960 and en passant file: u8 | half move: u16 | full move: u16 |
+    /// zero: u8[3], all little endian
+    ///
+    /// Pieces are color << 3 | kind, with rooks that can still castle stored as a separate kind
+    pub fn to_packed(&self) -> [u8; PACKED_SIZE] {
+        let mut packed = [0; PACKED_SIZE];
+        let occupancy = self.white_pieces | self.black_pieces;
+        packed[0..8].copy_from_slice(&occupancy.to_le_bytes());
+
+        let mut squares = occupancy;
+        let mut i = 0;
+        while squares != 0 {
+            let square = squares.trailing_zeros() as Square;
+            squares &= squares - 1;
+
+            let piece = self.get_piece(square);
+            let castling = (0..4).any(|index| {
+                self.castling_rights & (0b1000 >> index) != 0
+                    && self.castling_rooks[index] == square
+            });
+            let kind = if castling {
+                CASTLING_ROOK
+            } else {
+                piece.kind as u8
+            };
+            let code = (piece.color as u8) << 3 | kind;
+
+            packed[8 + i / 2] |= code << (4 * (i % 2));
+            i += 1;
+        }
+
+        let en_passant = if self.ep == -1 {
+            NO_EN_PASSANT
+        } else {
+            (self.ep % 8) as u8
+        };
+        packed[24] =
+            (self.turn == Color::Black) as u8 | (self.chess960 as u8) << 1 | en_passant << 4;
+        packed[25..27].copy_from_slice(&self.half_move_clock.to_le_bytes());
+        packed[27..29].copy_from_slice(&self.full_move_clock.to_le_bytes());
+
+        packed
+    }
+
+    pub fn from_packed(packed: &[u8; PACKED_SIZE]) -> Result<Board, PackedError> {
+        let mut board = Board::new();
+
+        let mut squares = u64::from_le_bytes(packed[0..8].try_into().unwrap());
+        if squares.count_ones() > 32 {
+            return Err(PackedError::TooManyPieces);
+        }
+
+        let mut castling_rooks = Vec::new();
+        let mut i = 0;
+        while squares != 0 {
+            let square = squares.trailing_zeros() as Square;
+            squares &= squares - 1;
+
+            let code = packed[8 + i / 2] >> (4 * (i % 2)) & 0xf;
+            i += 1;
+
+            let color = if code >> 3 == 0 {
+                Color::White
+            } else {
+                Color::Black
+            };
+            let kind = kind_from_code(code & 0b111).ok_or(PackedError::InvalidPiece(code))?;
+            if code & 0b111 == CASTLING_ROOK {
+                castling_rooks.push((color, square));
+            }
+            board.toggle_piece(Piece { color, kind }, square);
+        }
+
+        board.turn = if packed[24] & 1 == 0 {
+            Color::White
+        } else {
+            Color::Black
+        };
+        board.chess960 = packed[24] & 0b10 != 0;
+
+        board.castling_rights = 0;
+        board.castling_rooks = [7, 0, 63, 56];
+        for (color, rook) in castling_rooks {
+            let king = match color {
+                Color::White => board.kings & board.white_pieces,
+                Color::Black => board.kings & board.black_pieces,
+                Color::None => unreachable!(),
+            };
+            if king.count_ones() != 1 {
+                return Err(PackedError::InvalidCastling);
+            }
+            let king_side = rook % 8 > king.trailing_zeros() as Square % 8;
+            let index = match (color, king_side) {
+                (Color::White, true) => 0,
+                (Color::White, false) => 1,
+                (Color::Black, true) => 2,
+                _ => 3,
+            };
+            if board.castling_rights & (0b1000 >> index) != 0 {
+                return Err(PackedError::InvalidCastling);
+            }
+            board.castling_rights |= 0b1000 >> index;
+            board.castling_rooks[index] = rook;
+        }
+
+        board.ep = match packed[24] >> 4 {
+            NO_EN_PASSANT => -1,
+            // The square the pawn skipped, behind it from the side to move's point of view
+            file @ 0..8 => match board.turn {
+                Color::White => 40 + file as Square,
+                Color::Black => 16 + file as Square,
+                Color::None => unreachable!(),
+            },
+            _ => return Err(PackedError::InvalidEnPassant),
+        };
+
+        board.half_move_clock = u16::from_le_bytes(packed[25..27].try_into().unwrap());
+        board.full_move_clock = u16::from_le_bytes(packed[27..29].try_into().unwrap());
+
+        board.calculate_zobrist();
+        board.validate().map_err(PackedError::IllegalPosition)?;
+        Ok(board)
+    }
+}
+
+/// Writes positions one after the other behind a short header
+pub struct PackedWriter<W: Write> {
+    writer: W,
+}
+
+impl PackedWriter<BufWriter<File>> {
+    pub fn create<P: AsRef<Path>>(path: P) -> io::Result<Self> {
+        PackedWriter::new(BufWriter::new(File::create(path)?))
+    }
+}
+
+impl<W: Write> PackedWriter<W> {
+    pub fn new(mut writer: W) -> io::Result<Self> {
+        writer.write_all(&MAGIC)?;
+        Ok(PackedWriter { writer })
+    }
+
+    pub fn write(&mut self, board: &Board) -> io::Result<()> {
+        self.writer.write_all(&board.to_packed())
+    }
+
+    pub fn flush(&mut self) -> io::Result<()> {
+        self.writer.flush()
+    }
+}
+
+/// Reads the positions of a file written by `PackedWriter` one at a time
+pub struct PackedReader<R> {
+    reader: R,
+}
+
+impl PackedReader<BufReader<File>> {
+    pub fn open<P: AsRef<Path>>(path: P) -> Result<Self, PackedError> {
+        PackedReader::new(BufReader::new(File::open(path).map_err(PackedError::Io)?))
+    }
+}
+
+impl<R: Read> PackedReader<R> {
+    pub fn new(mut reader: R) -> Result<Self, PackedError> {
+        let mut magic = [0; MAGIC.len()];
+        reader
+            .read_exact(&mut magic)
+            .map_err(|err| match err.kind() {
+                io::ErrorKind::UnexpectedEof => PackedError::InvalidMagic,
+                _ => PackedError::Io(err),
+            })?;
+        if magic != MAGIC {
+            return Err(PackedError::InvalidMagic);
+        }
+        Ok(PackedReader { reader })
+    }
+}
+
+impl<R: Read> Iterator for PackedReader<R> {
+    type Item = Result<Board, PackedError>;
+
+    fn next(&mut self) -> Option<Self::Item> {
+        let mut packed = [0; PACKED_SIZE];
+        let mut read = 0;
+        while read < PACKED_SIZE {
+            match self.reader.read(&mut packed[read..]) {
+                Ok(0) => break,
+                Ok(n) => read += n,
+                Err(err) if err.kind() == io::ErrorKind::Interrupted => (),
+                Err(err) => return Some(Err(PackedError::Io(err))),
+            }
+        }
+
+        match read {
+            0 => None,
+            PACKED_SIZE => Some(Board::from_packed(&packed)),
+            _ => Some(Err(PackedError::Truncated)),
+        }
+    }
+}
@@ -1,4 +1,4 @@
-use crate::{board::Board, r#move::Move};
+use crate::{board::Board, epd::Epd, r#move::Move, zobrist::ZOBRIST_KEYS};
 
 use serde::Deserialize;
 use vampirc_uci::UciFen;
@@ -16,11 +16,23 @@ pub struct Position {
     pub depths: HashMap<u8, HashMap<String, usize>>,
 }
 
-pub fn perft_test(max_depth: u8, fen: Option<String>) {
+pub fn perft_test(max_depth: u8, fen: Option<String>, epd: Option<String>) {
     let mut board = Board::new();
 
+    if let Some(epd) = epd {
+        perft_epd(max_depth, &epd);
+        return;
+    }
+
     if let Some(fen) = fen {
-        board.load_position(Some(UciFen(fen.clone())), Vec::new());
+        if let Err(err) = board.load_position(Some(UciFen(fen.clone())), Vec::new()) {
+            eprintln!("Invalid fen: {err}");
+            return;
+        }
+        if let Err(err) = board.validate() {
+            eprintln!("Illegal position: {err}");
+            return;
+        }
         let perft = board.perft(max_depth, Move::NULL);
         let mut stockfish = setup_stockfish();
         let stockfish_perft = stockfish_perft(max_depth, &fen, Vec::new(), &mut stockfish);
@@ -36,7 +48,13 @@ pub fn perft_test(max_depth: u8, fen: Option<String>) {
     let mut total = 0;
 
     for p in &positions {
-        board.load_position(Some(UciFen(p.fen.clone())), Vec::new());
+        board
+            .load_position(Some(UciFen(p.fen.clone())), Vec::new())
+            .expect("Invalid fen in dataset");
+        if let Err(err) = board.validate() {
+            eprintln!("Skipping {}: {err}", p.fen);
+            continue;
+        }
 
         for (depth, stockfish_result) in p.depths.clone() {
             if depth > max_depth {
@@ -56,11 +74,59 @@ pub fn perft_test(max_depth: u8, fen: Option<String>) {
     println!("Test successful!");
 }
 
+/// Checks against the D<n> node counts of an EPD file like perftsuite.epd
+fn perft_epd(max_depth: u8, path: &str) {
+    let data = match fs::read_to_string(path) {
+        Ok(data) => data,
+        Err(err) => {
+            eprintln!("Failed to read {path}: {err}");
+            return;
+        }
+    };
+
+    let mut total = 0;
+
+    for line in data.lines().filter(|line| !line.trim().is_empty()) {
+        let mut epd = match Epd::parse(line) {
+            Ok(epd) => epd,
+            Err(err) => {
+                eprintln!("Skipping {line}: {err}");
+                continue;
+            }
+        };
+        let fen = epd.board.to_fen();
+
+        for &(depth, expected) in &epd.perft {
+            if depth > max_depth {
+                continue;
+            }
+            let perft = epd.board.perft(depth, Move::NULL);
+            total += perft.nodes;
+            if perft.nodes != expected {
+                println!("{fen}: expected {expected} nodes at depth {depth}, found {}", perft.nodes);
+                let mut stockfish = setup_stockfish();
+                let stockfish_perft = stockfish_perft(depth, &fen, Vec::new(), &mut stockfish);
+                quit_stockfish(&mut stockfish);
+                epd.board.difference(perft, stockfish_perft, &fen, depth);
+            }
+        }
+    }
+    println!("Nodes searched: {total}");
+    println!("Test successful!");
+}
+
 pub fn zobrist_test(max_depth: u8, fen: Option<String>) {
     let mut board = Board::new();
 
     if let Some(fen) = fen {
-        board.load_position(Some(UciFen(fen.clone())), Vec::new());
+        if let Err(err) = board.load_position(Some(UciFen(fen.clone())), Vec::new()) {
+            eprintln!("Invalid fen: {err}");
+            return;
+        }
+        if let Err(err) = board.validate() {
+            eprintln!("Illegal position: {err}");
+            return;
+        }
         board.perft_zobrist(max_depth, &fen, max_depth);
         println!("Test successful!");
         return;
@@ -69,8 +135,14 @@ pub fn zobrist_test(max_depth: u8, fen: Option<String>) {
     let data = fs::read_to_string("./chess-position-generator/perft_dataset.json").unwrap();
     let positions: Vec<Position> = serde_json::from_str(&data).unwrap();
 
-    for p in &positions[0..100] {
-        board.load_position(Some(UciFen(p.fen.clone())), Vec::new());
+    for p in positions.iter().take(100) {
+        board
+            .load_position(Some(UciFen(p.fen.clone())), Vec::new())
+            .expect("Invalid fen in dataset");
+        if let Err(err) = board.validate() {
+            eprintln!("Skipping {}: {err}", p.fen);
+            continue;
+        }
 
         for depth in 1..=max_depth {
             board.perft_zobrist(depth, &p.fen, depth);
@@ -79,6 +151,41 @@ pub fn zobrist_test(max_depth: u8, fen: Option<String>) {
     println!("Test successful!");
 }
 
+/// Checks that captures and quiets, or evasions when in check, add up to all moves
+pub fn staged_test(max_depth: u8, fen: Option<String>) {
+    let mut board = Board::new();
+
+    if let Some(fen) = fen {
+        if let Err(err) = board.load_position(Some(UciFen(fen.clone())), Vec::new()) {
+            eprintln!("Invalid fen: {err}");
+            return;
+        }
+        if let Err(err) = board.validate() {
+            eprintln!("Illegal position: {err}");
+            return;
+        }
+        board.perft_staged(max_depth, &fen, max_depth);
+        println!("Test successful!");
+        return;
+    }
+
+    let data = fs::read_to_string("./chess-position-generator/perft_dataset.json").unwrap();
+    let positions: Vec<Position> = serde_json::from_str(&data).unwrap();
+
+    for p in &positions {
+        board
+            .load_position(Some(UciFen(p.fen.clone())), Vec::new())
+            .expect("Invalid fen in dataset");
+        if let Err(err) = board.validate() {
+            eprintln!("Skipping {}: {err}", p.fen);
+            continue;
+        }
+
+        board.perft_staged(max_depth, &p.fen, max_depth);
+    }
+    println!("Test successful!");
+}
+
 #[derive(Default, Clone, Debug)]
 struct PerftResult {
     m: Move,
@@ -97,10 +204,10 @@ impl std::fmt::Display for PerftResult {
 }
 
 impl PerftResult {
-    /// NOTE: Ignores flags
+    /// NOTE: Compares the UCI notation since castling is stored as the king capturing its rook
     fn contains_move(&self, m: Move) -> bool {
         for result in &self.results {
-            if result.m.0 & 0b0000111111111111 == m.0 & 0b0000111111111111 {
+            if result.m.to_string() == m.to_string() {
                 return true;
             }
         }
@@ -111,10 +218,10 @@ impl PerftResult {
         Self::default()
     }
 
-    /// NOTE: Ignores flags
+    /// NOTE: Compares the UCI notation since castling is stored as the king capturing its rook
     fn get(&self, m: Move) -> Option<PerftResult> {
         for result in &self.results {
-            if result.m.0 & 0b0000111111111111 == m.0 & 0b0000111111111111 {
+            if result.m.to_string() == m.to_string() {
                 return Some(result.clone());
             }
         }
@@ -274,22 +381,21 @@ impl Board {
             self.unmake_move(m);
             if self.zobrist_hash != zobrist {
                 eprintln!("Zobrist not matching: {m}");
+                eprintln!("Position: {}", self.to_fen());
                 eprintln!("Debug command:");
                 eprintln!("cargo run --release -- perft {max_depth} --fen \"{fen}\" --zobrist");
                 self.print();
-                for i in 0..self.zobrist_values.len() {
-                    if self.zobrist_hash ^ self.zobrist_values[i] == zobrist {
-                        eprintln!("self.zobrist_hash ^ self.zobrist_values[{i}] == expected");
+                for (i, key) in ZOBRIST_KEYS.iter().enumerate() {
+                    if self.zobrist_hash ^ key == zobrist {
+                        eprintln!("self.zobrist_hash ^ ZOBRIST_KEYS[{i}] == expected");
                         panic!();
                     }
                 }
-                for i in 0..self.zobrist_values.len() {
-                    for j in 0..self.zobrist_values.len() {
-                        if self.zobrist_hash ^ self.zobrist_values[i] ^ self.zobrist_values[j]
-                            == zobrist
-                        {
+                for (i, key_i) in ZOBRIST_KEYS.iter().enumerate() {
+                    for (j, key_j) in ZOBRIST_KEYS.iter().enumerate() {
+                        if self.zobrist_hash ^ key_i ^ key_j == zobrist {
                             eprintln!(
-                                "self.zobrist_hash ^ self.zobrist_values[{i}] ^ self.zobrist_values[{j}] == expected"
+                                "self.zobrist_hash ^ ZOBRIST_KEYS[{i}] ^ ZOBRIST_KEYS[{j}] == expected"
                             );
                             panic!();
                         }
@@ -300,12 +406,60 @@ impl Board {
         }
     }
 
+    fn perft_staged(&mut self, depth: u8, fen: &str, max_depth: u8) {
+        if depth == 0 {
+            return;
+        }
+        let moves = self.generate_moves().collect::<Vec<_>>();
+        let mut staged = if self.generate_moves().in_check {
+            self.generate_evasions().collect::<Vec<_>>()
+        } else {
+            let captures = self.generate_captures().collect::<Vec<_>>();
+            let quiets = self.generate_quiets().collect::<Vec<_>>();
+            for m in &captures {
+                if !m.is_capture() && !m.is_promotion() {
+                    eprintln!("Quiet move among the captures: {m}");
+                    eprintln!("Position: {}", self.to_fen());
+                    panic!();
+                }
+            }
+            for m in &quiets {
+                if m.is_capture() || m.is_promotion() {
+                    eprintln!("Capture among the quiets: {m}");
+                    eprintln!("Position: {}", self.to_fen());
+                    panic!();
+                }
+            }
+            [captures, quiets].concat()
+        };
+        let mut sorted = moves.clone();
+        sorted.sort_by_key(|m| m.to_string());
+        staged.sort_by_key(|m| m.to_string());
+        if sorted != staged {
+            eprintln!("Staged moves not matching");
+            eprintln!("Position: {}", self.to_fen());
+            eprintln!("Expected: {sorted:?}");
+            eprintln!("Found: {staged:?}");
+            eprintln!("Debug command:");
+            eprintln!("cargo run --release -- perft {max_depth} --fen \"{fen}\" --staged");
+            self.print();
+            panic!();
+        }
+
+        for m in moves {
+            self.make_move(m);
+            self.perft_staged(depth - 1, fen, max_depth);
+            self.unmake_move(m);
+        }
+    }
+
     fn difference(&mut self, perft: PerftResult, stockfish: PerftResult, fen: &str, depth: u8) {
         for perft_result in &perft.results {
             let PerftResult { m, nodes, .. } = perft_result;
             if !stockfish.contains_move(*m) {
                 println!("Extra move!");
                 self.print();
+                println!("Position: {}", self.to_fen());
                 println!("{m}");
                 println!("Debug command:");
                 println!("cargo run --release -- perft {depth} --fen \"{fen}\"");
@@ -324,6 +478,7 @@ impl Board {
             if !perft.contains_move(*m) {
                 println!("Move missing!");
                 self.print();
+                println!("Position: {}", self.to_fen());
                 println!("{m}");
                 println!("Debug command:");
                 println!("cargo run --release -- perft {depth} --fen \"{fen}\"");
@@ -0,0 +1,371 @@
+use std::{
+    fs::File,
+    io::{self, BufRead, BufReader, Lines},
+    path::Path,
+};
+
+use crate::{
+    board::{Board, Color},
+    fen::{FenError, STARTPOS},
+    r#move::Move,
+    san::SanError,
+    search::Score,
+    validate::PositionError,
+};
+
+const SEVEN_TAG_ROSTER: [(&str, &str); 7] = [
+    ("Event", "?"),
+    ("Site", "?"),
+    ("Date", "????.??.??"),
+    ("Round", "?"),
+    ("White", "?"),
+    ("Black", "?"),
+    ("Result", "*"),
+];
+
+// Export format keeps lines below 80 characters
+const LINE_LENGTH: usize = 79;
+
+#[derive(Debug)]
+pub enum PgnError {
+    Io(io::Error),
+    InvalidTag(String),
+    UnterminatedComment,
+    UnbalancedVariation,
+    Fen(FenError),
+    IllegalPosition(PositionError),
+    Move { ply: usize, err: SanError },
+}
+
+impl std::fmt::Display for PgnError {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        match self {
+            PgnError::Io(err) => write!(f, "{err}"),
+            PgnError::InvalidTag(tag) => write!(f, "invalid tag pair '{tag}'"),
+            PgnError::UnterminatedComment => write!(f, "unterminated comment"),
+            PgnError::UnbalancedVariation => write!(f, "unbalanced parentheses in variation"),
+            PgnError::Fen(err) => write!(f, "invalid FEN tag: {err}"),
+            PgnError::IllegalPosition(err) => write!(f, "illegal FEN tag: {err}"),
+            PgnError::Move { ply, err } => write!(f, "ply {}: {err}", ply + 1),
+        }
+    }
+}
+
+impl std::error::Error for PgnError {}
+
+#[derive(Debug, Clone)]
+pub struct Game {
+    // In the order they appear, starting with the Seven Tag Roster
+    pub headers: Vec<(String, String)>,
+    // The position each move was played in, followed by the final position
+    pub positions: Vec<Board>,
+    pub moves: Vec<Move>,
+    // The game termination marker, 1-0, 0-1, 1/2-1/2 or *
+    pub result: String,
+}
+
+impl Game {
+    /// A game without moves, with unknown values in the Seven Tag Roster
+    pub fn new(start: Board) -> Game {
+        let mut headers = SEVEN_TAG_ROSTER
+            .iter()
+            .map(|(name, value)| (name.to_string(), value.to_string()))
+            .collect::<Vec<_>>();
+        if start.chess960 {
+            headers.push((String::from("Variant"), String::from("Chess960")));
+        }
+        let fen = start.to_fen();
+        if fen != STARTPOS {
+            headers.push((String::from("SetUp"), String::from("1")));
+            headers.push((String::from("FEN"), fen));
+        }
+
+        Game {
+            headers,
+            positions: vec![start],
+            moves: Vec::new(),
+            result: String::from("*"),
+        }
+    }
+
+    pub fn header(&self, name: &str) -> Option<&str> {
+        self.headers
+            .iter()
+            .find(|(tag, _)| tag == name)
+            .map(|(_, value)| value.as_str())
+    }
+
+    pub fn set_header(&mut self, name: &str, value: &str) {
+        match self.headers.iter_mut().find(|(tag, _)| tag == name) {
+            Some((_, old)) => *old = value.to_string(),
+            None => self.headers.push((name.to_string(), value.to_string())),
+        }
+    }
+
+    pub fn play(&mut self, m: Move) {
+        let mut board = self.positions.last().unwrap().clone();
+        board.make_move(m);
+        self.positions.push(board);
+        self.moves.push(m);
+    }
+
+    /// `evals` holds the search score, if any, for each move from the point of view of the side
+    /// that played it
+    pub fn to_pgn(&self, evals: &[Option<Score>]) -> String {
+        let mut pgn = String::new();
+        let escape = |value: &str| value.replace('\\', "\\\\").replace('"', "\\\"");
+        for (name, default) in SEVEN_TAG_ROSTER {
+            let value = match name {
+                "Result" => self.result.as_str(),
+                _ => self.header(name).unwrap_or(default),
+            };
+            pgn += &format!("[{name} \"{}\"]\n", escape(value));
+        }
+        for (name, value) in &self.headers {
+            if SEVEN_TAG_ROSTER.iter().all(|(roster, _)| roster != name) {
+                pgn += &format!("[{name} \"{}\"]\n", escape(value));
+            }
+        }
+        pgn.push('\n');
+
+        let mut tokens = Vec::new();
+        let mut after_comment = false;
+        for (i, m) in self.moves.iter().enumerate() {
+            let mut board = self.positions[i].clone();
+            // Black's move number is only repeated when something came in between
+            if board.turn == Color::White {
+                tokens.push(format!("{}.", board.full_move_clock));
+            } else if i == 0 || after_comment {
+                tokens.push(format!("{}...", board.full_move_clock));
+            }
+            tokens.push(board.move_to_san(*m));
+
+            let eval = evals
+                .get(i)
+                .copied()
+                .flatten()
+                .and_then(|score| score.pgn_eval(board.turn));
+            after_comment = eval.is_some();
+            if let Some(eval) = eval {
+                tokens.push(format!("{{[%eval {eval}]}}"));
+            }
+        }
+        tokens.push(self.result.clone());
+
+        let mut line = String::new();
+        for token in tokens {
+            if !line.is_empty() && line.len() + 1 + token.len() > LINE_LENGTH {
+                pgn += &line;
+                pgn.push('\n');
+                line.clear();
+            }
+            if !line.is_empty() {
+                line.push(' ');
+            }
+            line += &token;
+        }
+        pgn += &line;
+        pgn.push('\n');
+
+        pgn
+    }
+}
+
+/// Reads the games of a PGN file one at a time, replaying the mainline of each
+pub struct PgnReader<R> {
+    lines: Lines<R>,
+    // The first tag of the next game, read while looking for the end of the previous one
+    pending: Option<String>,
+}
+
+impl PgnReader<BufReader<File>> {
+    pub fn open<P: AsRef<Path>>(path: P) -> io::Result<Self> {
+        Ok(PgnReader::new(BufReader::new(File::open(path)?)))
+    }
+}
+
+impl<R: BufRead> PgnReader<R> {
+    pub fn new(reader: R) -> Self {
+        PgnReader {
+            lines: reader.lines(),
+            pending: None,
+        }
+    }
+}
+
+impl<R: BufRead> Iterator for PgnReader<R> {
+    type Item = Result<Game, PgnError>;
+
+    fn next(&mut self) -> Option<Self::Item> {
+        let mut text = self.pending.take().unwrap_or_default();
+        let mut in_movetext = false;
+        let mut in_comment = false;
+
+        loop {
+            let line = match self.lines.next() {
+                Some(Ok(line)) => line,
+                Some(Err(err)) => return Some(Err(PgnError::Io(err))),
+                None => break,
+            };
+            let trimmed = line.trim_start_matches('\u{feff}').trim_start();
+
+            // A tag after the movetext belongs to the next game
+            if !in_comment && in_movetext && trimmed.starts_with('[') {
+                self.pending = Some(format!("{trimmed}\n"));
+                break;
+            }
+            if in_comment || !(trimmed.is_empty() || trimmed.starts_with(['[', '%'])) {
+                in_movetext = true;
+            }
+
+            // Comments can span lines, and can contain anything
+            let tag = !in_comment && trimmed.starts_with('[');
+            for chr in trimmed.chars().filter(|_| !tag) {
+                match chr {
+                    '{' if !in_comment => in_comment = true,
+                    '}' if in_comment => in_comment = false,
+                    ';' if !in_comment => break,
+                    _ => (),
+                }
+            }
+
+            text += trimmed;
+            text.push('\n');
+        }
+
+        if text.trim().is_empty() {
+            return None;
+        }
+        Some(parse_game(&text))
+    }
+}
+
+fn parse_tag(tag: &str) -> Result<(String, String), PgnError> {
+    let invalid = || PgnError::InvalidTag(tag.to_string());
+    let (name, value) = tag.trim().split_once(char::is_whitespace).ok_or_else(invalid)?;
+    let value = value
+        .trim()
+        .strip_prefix('"')
+        .and_then(|value| value.strip_suffix('"'))
+        .ok_or_else(invalid)?;
+    Ok((
+        name.to_string(),
+        value.replace("\\\"", "\"").replace("\\\\", "\\"),
+    ))
+}
+
+fn parse_game(text: &str) -> Result<Game, PgnError> {
+    let mut headers = Vec::new();
+    let mut sans = Vec::new();
+    let mut result = String::from("*");
+
+    let mut chars = text.chars().peekable();
+    let mut line_start = true;
+    let mut depth = 0;
+    while let Some(chr) = chars.next() {
+        let was_line_start = line_start;
+        line_start = chr == '\n';
+        match chr {
+            // Escaped lines are ignored entirely
+            '%' if was_line_start => {
+                chars.by_ref().find(|&chr| chr == '\n');
+                line_start = true;
+            }
+            '[' if sans.is_empty() => {
+                let mut tag = String::new();
+                let mut in_string = false;
+                let mut escaped = false;
+                for chr in chars.by_ref() {
+                    match chr {
+                        ']' if !in_string => break,
+                        '"' if !escaped => in_string = !in_string,
+                        _ => (),
+                    }
+                    escaped = chr == '\\' && !escaped;
+                    tag.push(chr);
+                }
+                headers.push(parse_tag(&tag)?);
+            }
+            '{' => {
+                chars
+                    .by_ref()
+                    .find(|&chr| chr == '}')
+                    .ok_or(PgnError::UnterminatedComment)?;
+            }
+            ';' => {
+                chars.by_ref().find(|&chr| chr == '\n');
+                line_start = true;
+            }
+            '(' => depth += 1,
+            ')' => {
+                if depth == 0 {
+                    return Err(PgnError::UnbalancedVariation);
+                }
+                depth -= 1;
+            }
+            // Numeric Annotation Glyphs
+            '$' => while chars.next_if(|chr| chr.is_ascii_digit()).is_some() {},
+            chr if chr.is_whitespace() => (),
+            chr => {
+                let mut token = String::from(chr);
+                while let Some(chr) =
+                    chars.next_if(|chr| !chr.is_whitespace() && !"{}();[$".contains(*chr))
+                {
+                    token.push(chr);
+                }
+                if depth > 0 {
+                    continue;
+                }
+
+                match token.as_str() {
+                    "1-0" | "0-1" | "1/2-1/2" | "*" => result = token,
+                    _ if token.starts_with("0-0") => sans.push(token),
+                    // Move numbers, possibly written without a space before the move
+                    _ => {
+                        let san = token.trim_start_matches(|chr: char| chr.is_ascii_digit());
+                        let san = if san.len() < token.len() {
+                            san.trim_start_matches('.')
+                        } else {
+                            san
+                        };
+                        if !san.is_empty() {
+                            sans.push(san.to_string());
+                        }
+                    }
+                }
+            }
+        }
+    }
+    if depth > 0 {
+        return Err(PgnError::UnbalancedVariation);
+    }
+
+    let fen = headers
+        .iter()
+        .find(|(tag, _)| tag == "FEN")
+        .map_or(STARTPOS, |(_, fen)| fen.as_str());
+    let mut board = Board::from_fen(fen).map_err(PgnError::Fen)?;
+    board.chess960 = headers
+        .iter()
+        .any(|(tag, variant)| tag == "Variant" && variant.eq_ignore_ascii_case("chess960"));
+    board.validate().map_err(PgnError::IllegalPosition)?;
+
+    let mut positions = Vec::with_capacity(sans.len() + 1);
+    let mut moves = Vec::with_capacity(sans.len());
+    for (ply, san) in sans.iter().enumerate() {
+        let m = board
+            .parse_san(san)
+            .map_err(|err| PgnError::Move { ply, err })?;
+        positions.push(board.clone());
+        board.make_move(m);
+        moves.push(m);
+    }
+    positions.push(board);
+
+    Ok(Game {
+        headers,
+        positions,
+        moves,
+        result,
+    })
+}
@@ -0,0 +1,126 @@
+use crate::board::{Color, Piece, PieceKind, Square};
+
+const WHITE_PAWN_SQUARE_TABLE: [i64; 64] = [
+    0, 0, 0, 0, 0, 0, 0, 0, 5, 10, 10, -20, -20, 10, 10, 5, 5, -5, -10, 0, 0, -10, -5, 5, 0, 0,
+    0, 20, 20, 0, 0, 0, 5, 5, 10, 25, 25, 10, 5, 5, 10, 10, 20, 30, 30, 20, 10, 10, 50, 50, 50,
+    50, 50, 50, 50, 50, 0, 0, 0, 0, 0, 0, 0, 0,
+];
+
+const BLACK_PAWN_SQUARE_TABLE: [i64; 64] = [
+    0, 0, 0, 0, 0, 0, 0, 0, 50, 50, 50, 50, 50, 50, 50, 50, 10, 10, 20, 30, 30, 20, 10, 10, 5,
+    5, 10, 25, 25, 10, 5, 5, 0, 0, 0, 20, 20, 0, 0, 0, 5, -5, -10, 0, 0, -10, -5, 5, 5, 10, 10,
+    -20, -20, 10, 10, 5, 0, 0, 0, 0, 0, 0, 0, 0,
+];
+const WHITE_KNIGHT_SQUARE_TABLE: [i64; 64] = [
+    -50, -40, -30, -30, -30, -30, -40, -50, -40, -20, 0, 5, 5, 0, -20, -40, -30, 5, 10, 15, 15,
+    10, 5, -30, -30, 0, 15, 20, 20, 15, 0, -30, -30, 5, 15, 20, 20, 15, 5, -30, -30, 0, 10, 15,
+    15, 10, 0, -30, -40, -20, 0, 0, 0, 0, -20, -40, -50, -40, -30, -30, -30, -30, -40, -50,
+];
+const BLACK_KNIGHT_SQUARE_TABLE: [i64; 64] = [
+    -50, -40, -30, -30, -30, -30, -40, -50, -40, -20, 0, 0, 0, 0, -20, -40, -30, 0, 10, 15, 15,
+    10, 0, -30, -30, 5, 15, 20, 20, 15, 5, -30, -30, 0, 15, 20, 20, 15, 0, -30, -30, 5, 10, 15,
+    15, 10, 5, -30, -40, -20, 0, 5, 5, 0, -20, -40, -50, -40, -30, -30, -30, -30, -40, -50,
+];
+const WHITE_BISHOP_SQUARE_TABLE: [i64; 64] = [
+    -20, -10, -10, -10, -10, -10, -10, -20, -10, 5, 0, 0, 0, 0, 5, -10, -10, 10, 10, 10, 10,
+    10, 10, -10, -10, 0, 10, 10, 10, 10, 0, -10, -10, 5, 5, 10, 10, 5, 5, -10, -10, 0, 5, 10,
+    10, 5, 0, -10, -10, 0, 0, 0, 0, 0, 0, -10, -20, -10, -10, -10, -10, -10, -10, -20,
+];
+const BLACK_BISHOP_SQUARE_TABLE: [i64; 64] = [
+    -20, -10, -10, -10, -10, -10, -10, -20, -10, 0, 0, 0, 0, 0, 0, -10, -10, 0, 5, 10, 10, 5,
+    0, -10, -10, 5, 5, 10, 10, 5, 5, -10, -10, 0, 10, 10, 10, 10, 0, -10, -10, 10, 10, 10, 10,
+    10, 10, -10, -10, 5, 0, 0, 0, 0, 5, -10, -20, -10, -10, -10, -10, -10, -10, -20,
+];
+const WHITE_ROOK_SQUARE_TABLE: [i64; 64] = [
+    0, 0, 0, 5, 5, 0, 0, 0, -5, 0, 0, 0, 0, 0, 0, -5, -5, 0, 0, 0, 0, 0, 0, -5, -5, 0, 0, 0, 0,
+    0, 0, -5, -5, 0, 0, 0, 0, 0, 0, -5, -5, 0, 0, 0, 0, 0, 0, -5, 5, 10, 10, 10, 10, 10, 10, 5,
+    0, 0, 0, 0, 0, 0, 0, 0,
+];
+const BLACK_ROOK_SQUARE_TABLE: [i64; 64] = [
+    0, 0, 0, 0, 0, 0, 0, 0, 5, 10, 10, 10, 10, 10, 10, 5, -5, 0, 0, 0, 0, 0, 0, -5, -5, 0, 0,
+    0, 0, 0, 0, -5, -5, 0, 0, 0, 0, 0, 0, -5, -5, 0, 0, 0, 0, 0, 0, -5, -5, 0, 0, 0, 0, 0, 0,
+    -5, 0, 0, 0, 5, 5, 0, 0, 0,
+];
+const WHITE_QUEEN_SQUARE_TABLE: [i64; 64] = [
+    -20, -10, -10, -5, -5, -10, -10, -20, -10, 0, 5, 0, 0, 0, 0, -10, -10, 5, 5, 5, 5, 5, 0,
+    -10, 0, 0, 5, 5, 5, 5, 0, -5, -5, 0, 5, 5, 5, 5, 0, -5, -10, 0, 5, 5, 5, 5, 0, -10, -10, 0,
+    0, 0, 0, 0, 0, -10, -20, -10, -10, -5, -5, -10, -10, -20,
+];
+const BLACK_QUEEN_SQUARE_TABLE: [i64; 64] = [
+    -20, -10, -10, -5, -5, -10, -10, -20, -10, 0, 0, 0, 0, 0, 0, -10, -10, 0, 5, 5, 5, 5, 0,
+    -10, -5, 0, 5, 5, 5, 5, 0, -5, 0, 0, 5, 5, 5, 5, 0, -5, -10, 5, 5, 5, 5, 5, 0, -10, -10, 0,
+    5, 0, 0, 0, 0, -10, -20, -10, -10, -5, -5, -10, -10, -20,
+];
+const WHITE_KING_SQUARE_TABLE: [i64; 64] = [
+    20, 30, 10, 0, 0, 10, 30, 20, 20, 20, 0, 0, 0, 0, 20, 20, -10, -20, -20, -20, -20, -20,
+    -20, -10, -20, -30, -30, -40, -40, -30, -30, -20, -30, -40, -40, -50, -50, -40, -40, -30,
+    -30, -40, -40, -50, -50, -40, -40, -30, -30, -40, -40, -50, -50, -40, -40, -30, -30, -40,
+    -40, -50, -50, -40, -40, -30,
+];
+const BLACK_KING_SQUARE_TABLE: [i64; 64] = [
+    -30, -40, -40, -50, -50, -40, -40, -30, -30, -40, -40, -50, -50, -40, -40, -30, -30, -40,
+    -40, -50, -50, -40, -40, -30, -30, -40, -40, -50, -50, -40, -40, -30, -20, -30, -30, -40,
+    -40, -30, -30, -20, -10, -20, -20, -20, -20, -20, -20, -10, 20, 20, 0, 0, 0, 0, 20, 20, 20,
+    30, 10, 0, 0, 10, 30, 20,
+];
+
+/// The piece-square table bonus of a piece, positive for white
+pub fn value(piece: Piece, square: Square) -> i64 {
+    let table = match piece {
+        Piece {
+            color: Color::White,
+            kind: PieceKind::Pawn,
+        } => WHITE_PAWN_SQUARE_TABLE,
+        Piece {
+            color: Color::Black,
+            kind: PieceKind::Pawn,
+        } => BLACK_PAWN_SQUARE_TABLE,
+        Piece {
+            color: Color::White,
+            kind: PieceKind::Rook,
+        } => WHITE_ROOK_SQUARE_TABLE,
+        Piece {
+            color: Color::Black,
+            kind: PieceKind::Rook,
+        } => BLACK_ROOK_SQUARE_TABLE,
+        Piece {
+            color: Color::White,
+            kind: PieceKind::Knight,
+        } => WHITE_KNIGHT_SQUARE_TABLE,
+        Piece {
+            color: Color::Black,
+            kind: PieceKind::Knight,
+        } => BLACK_KNIGHT_SQUARE_TABLE,
+        Piece {
+            color: Color::White,
+            kind: PieceKind::Bishop,
+        } => WHITE_BISHOP_SQUARE_TABLE,
+        Piece {
+            color: Color::Black,
+            kind: PieceKind::Bishop,
+        } => BLACK_BISHOP_SQUARE_TABLE,
+        Piece {
+            color: Color::White,
+            kind: PieceKind::Queen,
+        } => WHITE_QUEEN_SQUARE_TABLE,
+        Piece {
+            color: Color::Black,
+            kind: PieceKind::Queen,
+        } => BLACK_QUEEN_SQUARE_TABLE,
+        Piece {
+            color: Color::White,
+            kind: PieceKind::King,
+        } => WHITE_KING_SQUARE_TABLE,
+        Piece {
+            color: Color::Black,
+            kind: PieceKind::King,
+        } => BLACK_KING_SQUARE_TABLE,
+        _ => unreachable!(),
+    };
+    table[square as usize]
+        * match piece.color {
+            Color::White => 1,
+            Color::Black => -1,
+            Color::None => unreachable!(),
+        }
+}
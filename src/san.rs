@@ -0,0 +1,188 @@
+use crate::board::{AsSquare, Board, CastleKind, PieceKind, Square, parse_square};
+use crate::r#move::Move;
+
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum SanError {
+    InvalidSyntax(String),
+    IllegalMove(String),
+    AmbiguousMove(String),
+}
+
+impl std::fmt::Display for SanError {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        match self {
+            SanError::InvalidSyntax(san) => write!(f, "invalid SAN '{san}'"),
+            SanError::IllegalMove(san) => write!(f, "illegal move '{san}'"),
+            SanError::AmbiguousMove(san) => write!(f, "ambiguous move '{san}'"),
+        }
+    }
+}
+
+impl std::error::Error for SanError {}
+
+fn piece_letter(kind: PieceKind) -> Option<char> {
+    match kind {
+        PieceKind::Rook => Some('R'),
+        PieceKind::Knight => Some('N'),
+        PieceKind::Bishop => Some('B'),
+        PieceKind::Queen => Some('Q'),
+        PieceKind::King => Some('K'),
+        _ => None,
+    }
+}
+
+fn letter_piece(letter: char) -> Option<PieceKind> {
+    match letter {
+        'R' => Some(PieceKind::Rook),
+        'N' => Some(PieceKind::Knight),
+        'B' => Some(PieceKind::Bishop),
+        'Q' => Some(PieceKind::Queen),
+        'K' => Some(PieceKind::King),
+        _ => None,
+    }
+}
+
+impl Board {
+    pub fn move_to_san(&mut self, m: Move) -> String {
+        let mut san = match m.castle() {
+            CastleKind::KingSide => String::from("O-O"),
+            CastleKind::QueenSide => String::from("O-O-O"),
+            CastleKind::None => self.piece_move_to_san(m),
+        };
+
+        if self.gives_check(m) {
+            self.make_move(m);
+            let mate = self.generate_moves().is_empty();
+            self.unmake_move(m);
+            san.push(if mate { '#' } else { '+' });
+        }
+
+        san
+    }
+
+    fn piece_move_to_san(&mut self, m: Move) -> String {
+        let from = m.from();
+        let to = m.to();
+        let kind = self.get_piece(from).kind;
+
+        let mut san = String::new();
+        match piece_letter(kind) {
+            Some(letter) => {
+                san.push(letter);
+
+                // Only name as much of the from square as is needed to tell the moves apart
+                let others = self
+                    .generate_moves()
+                    .filter(|other| {
+                        other.to() == to
+                            && other.from() != from
+                            && !other.is_castle()
+                            && self.get_piece(other.from()).kind == kind
+                    })
+                    .collect::<Vec<_>>();
+                if !others.is_empty() {
+                    let from_square = from.as_square();
+                    if others.iter().all(|other| other.from() % 8 != from % 8) {
+                        san.push_str(&from_square[..1]);
+                    } else if others.iter().all(|other| other.from() / 8 != from / 8) {
+                        san.push_str(&from_square[1..]);
+                    } else {
+                        san.push_str(&from_square);
+                    }
+                }
+            }
+            // Pawn captures are always written with the file they came from
+            None if m.is_capture() => san.push_str(&from.as_square()[..1]),
+            None => (),
+        }
+
+        if m.is_capture() {
+            san.push('x');
+        }
+        san.push_str(&to.as_square());
+
+        if let Some(letter) = piece_letter(m.promotion()) {
+            san.push('=');
+            san.push(letter);
+        }
+
+        san
+    }
+
+    pub fn parse_san(&mut self, san: &str) -> Result<Move, SanError> {
+        // Check markers and annotations like ! and ?! are not needed to find the move
+        let stripped = san.trim_end_matches(['+', '#', '!', '?']);
+
+        let castle = match stripped {
+            "O-O" | "0-0" => Some(CastleKind::KingSide),
+            "O-O-O" | "0-0-0" => Some(CastleKind::QueenSide),
+            _ => None,
+        };
+        if let Some(castle) = castle {
+            return self
+                .generate_moves()
+                .find(|m| m.castle() == castle)
+                .ok_or(SanError::IllegalMove(san.to_string()));
+        }
+
+        let invalid = || SanError::InvalidSyntax(san.to_string());
+
+        let mut chars = stripped.chars().filter(|&chr| chr != 'x').collect::<Vec<_>>();
+
+        let kind = match chars.first().and_then(|&letter| letter_piece(letter)) {
+            Some(kind) => {
+                chars.remove(0);
+                kind
+            }
+            None => PieceKind::Pawn,
+        };
+
+        // Both e8=Q and e8Q are in use
+        let promotion = match chars.last().and_then(|&letter| letter_piece(letter)) {
+            Some(promotion) if kind == PieceKind::Pawn && promotion != PieceKind::King => {
+                chars.pop();
+                if chars.last() == Some(&'=') {
+                    chars.pop();
+                }
+                promotion
+            }
+            _ => PieceKind::None,
+        };
+
+        if chars.len() < 2 || chars.len() > 4 {
+            return Err(invalid());
+        }
+        let to = parse_square(&chars[chars.len() - 2..].iter().collect::<String>())
+            .ok_or_else(invalid)?;
+
+        let mut from_file = None;
+        let mut from_rank = None;
+        for &chr in &chars[..chars.len() - 2] {
+            match chr {
+                'a'..='h' if from_file.is_none() && from_rank.is_none() => {
+                    from_file = Some(chr as Square - 'a' as Square)
+                }
+                '1'..='8' if from_rank.is_none() => from_rank = Some(chr as Square - '1' as Square),
+                _ => return Err(invalid()),
+            }
+        }
+
+        let candidates = self
+            .generate_moves()
+            .filter(|m| {
+                m.to() == to
+                    && !m.is_castle()
+                    && m.promotion() == promotion
+                    && self.get_piece(m.from()).kind == kind
+                    && from_file.is_none_or(|file| m.from() % 8 == file)
+                    && from_rank.is_none_or(|rank| m.from() / 8 == rank)
+            })
+            .collect::<Vec<_>>();
+
+        match candidates[..] {
+            [m] => Ok(m),
+            [] => Err(SanError::IllegalMove(san.to_string())),
+            _ => Err(SanError::AmbiguousMove(san.to_string())),
+        }
+    }
+}
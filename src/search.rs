@@ -9,8 +9,9 @@ use std::{
 use vampirc_uci::{UciSearchControl, UciTimeControl};
 
 use crate::{
-    board::{Board, Color, Piece, PieceKind},
+    board::{Board, Color},
     r#move::Move,
+    move_generator::MoveGeneratorResult,
     uci::Status,
 };
 
@@ -42,6 +43,8 @@ pub struct Search {
     tt_hits: usize,
 
     nodes: usize,
+    // Set by `go nodes`, the search stops once it is reached
+    max_nodes: usize,
 
     pub score: Score,
 
@@ -259,6 +262,22 @@ impl Score {
         }
     }
 
+    /// The `[%eval]` notation of PGN comments, from white's point of view and in whole moves
+    pub fn pgn_eval(self, turn: Color) -> Option<String> {
+        let score = match turn {
+            Color::White => self,
+            Color::Black => -self,
+            Color::None => unreachable!(),
+        };
+        match score {
+            Score::OwnMate(ply) => Some(format!("#{}", ply.div_ceil(2))),
+            Score::OppMate(ply) => Some(format!("#-{}", ply.div_ceil(2))),
+            Score::Score(score) => Some(format!("{:.2}", score as f64 / 100f64)),
+            Score::Draw(_) => Some(String::from("0.00")),
+            Score::Stop => None,
+        }
+    }
+
     fn flip_score(self) -> Score {
         match self {
             Score::OwnMate(ply) => Score::OwnMate(ply),
@@ -291,6 +310,7 @@ impl Search {
             tt_hits: 0,
 
             nodes: 0,
+            max_nodes: usize::MAX,
 
             score: Score::default(),
 
@@ -300,6 +320,11 @@ impl Search {
         }
     }
 
+    /// Evaluates a position without searching, from the side to move's point of view
+    pub fn static_eval(board: Board) -> Score {
+        Self::new(Arc::new(RwLock::new(Status::Go)), board).eval()
+    }
+
     pub fn go(
         board: Board,
         search_control: Option<UciSearchControl>,
@@ -321,6 +346,7 @@ impl Search {
                     Color::Black => black_time.num_milliseconds() / 20,
                     Color::None => unreachable!(),
                 },
+                UciTimeControl::MoveTime(move_time) => move_time.num_milliseconds(),
                 _ => 0,
             };
             if move_time != 0 {
@@ -340,6 +366,15 @@ impl Search {
             }) => depth,
             _ => u8::MAX,
         };
+        if let Some(UciSearchControl {
+            nodes: Some(nodes), ..
+        }) = search_control
+        {
+            search.max_nodes = nodes as usize;
+        }
+
+        // Stopping before depth 1 finishes still leaves a legal move to play
+        search.pv = search.board.generate_moves().next().unwrap_or(Move::NULL);
 
         let mut depth = 1;
         while *search.stopper.read().unwrap() != Status::Stopping && depth <= max_depth {
@@ -355,8 +390,12 @@ impl Search {
 
                 (score, node_kind) = search.negamax(search.depth, alpha, beta);
                 eprintln!(
-                    "{depth}: {}({}) <= {score} <= {}({}) {node_kind:?}",
-                    alpha, window.0, beta, window.1
+                    "{depth}: {}({}) <= {score} <= {}({}) {node_kind:?} {}",
+                    alpha,
+                    window.0,
+                    beta,
+                    window.1,
+                    search.board.to_fen()
                 );
 
                 eprintln!(
@@ -394,162 +433,15 @@ impl Search {
         search
     }
 
-    const WHITE_PAWN_SQUARE_TABLE: [i64; 64] = [
-        0, 0, 0, 0, 0, 0, 0, 0, 5, 10, 10, -20, -20, 10, 10, 5, 5, -5, -10, 0, 0, -10, -5, 5, 0, 0,
-        0, 20, 20, 0, 0, 0, 5, 5, 10, 25, 25, 10, 5, 5, 10, 10, 20, 30, 30, 20, 10, 10, 50, 50, 50,
-        50, 50, 50, 50, 50, 0, 0, 0, 0, 0, 0, 0, 0,
-    ];
-
-    const BLACK_PAWN_SQUARE_TABLE: [i64; 64] = [
-        0, 0, 0, 0, 0, 0, 0, 0, 50, 50, 50, 50, 50, 50, 50, 50, 10, 10, 20, 30, 30, 20, 10, 10, 5,
-        5, 10, 25, 25, 10, 5, 5, 0, 0, 0, 20, 20, 0, 0, 0, 5, -5, -10, 0, 0, -10, -5, 5, 5, 10, 10,
-        -20, -20, 10, 10, 5, 0, 0, 0, 0, 0, 0, 0, 0,
-    ];
-    const KNIGHT_SQUARE_TABLE: [i64; 64] = [
-        -50, -40, -30, -30, -30, -30, -40, -50, -40, -20, 0, 5, 5, 0, -20, -40, -30, 5, 10, 15, 15,
-        10, 5, -30, -30, 0, 15, 20, 20, 15, 0, -30, -30, 5, 15, 20, 20, 15, 5, -30, -30, 0, 10, 15,
-        15, 10, 0, -30, -40, -20, 0, 0, 0, 0, -20, -40, -50, -40, -30, -30, -30, -30, -40, -50,
-    ];
-    const WHITE_BISHOP_SQUARE_TABLE: [i64; 64] = [
-        -20, -10, -10, -10, -10, -10, -10, -20, -10, 5, 0, 0, 0, 0, 5, -10, -10, 10, 10, 10, 10,
-        10, 10, -10, -10, 0, 10, 10, 10, 10, 0, -10, -10, 5, 5, 10, 10, 5, 5, -10, -10, 0, 5, 10,
-        10, 5, 0, -10, -10, 0, 0, 0, 0, 0, 0, -10, -20, -10, -10, -10, -10, -10, -10, -20,
-    ];
-    const BLACK_BISHOP_SQUARE_TABLE: [i64; 64] = [
-        -20, -10, -10, -10, -10, -10, -10, -20, -10, 0, 0, 0, 0, 0, 0, -10, -10, 0, 5, 10, 10, 5,
-        0, -10, -10, 5, 5, 10, 10, 5, 5, -10, -10, 0, 10, 10, 10, 10, 0, -10, -10, 10, 10, 10, 10,
-        10, 10, -10, -10, 5, 0, 0, 0, 0, 5, -10, -20, -10, -10, -10, -10, -10, -10, -20,
-    ];
-    const WHITE_ROOK_SQUARE_TABLE: [i64; 64] = [
-        0, 0, 0, 5, 5, 0, 0, 0, -5, 0, 0, 0, 0, 0, 0, -5, -5, 0, 0, 0, 0, 0, 0, -5, -5, 0, 0, 0, 0,
-        0, 0, -5, -5, 0, 0, 0, 0, 0, 0, -5, -5, 0, 0, 0, 0, 0, 0, -5, 5, 10, 10, 10, 10, 10, 10, 5,
-        0, 0, 0, 0, 0, 0, 0, 0,
-    ];
-    const BLACK_ROOK_SQUARE_TABLE: [i64; 64] = [
-        0, 0, 0, 0, 0, 0, 0, 0, 5, 10, 10, 10, 10, 10, 10, 5, -5, 0, 0, 0, 0, 0, 0, -5, -5, 0, 0,
-        0, 0, 0, 0, -5, -5, 0, 0, 0, 0, 0, 0, -5, -5, 0, 0, 0, 0, 0, 0, -5, -5, 0, 0, 0, 0, 0, 0,
-        -5, 0, 0, 0, 5, 5, 0, 0, 0,
-    ];
-    const WHITE_QUEEN_SQUARE_TABLE: [i64; 64] = [
-        -20, -10, -10, -5, -5, -10, -10, -20, -10, 0, 5, 0, 0, 0, 0, -10, -10, 5, 5, 5, 5, 5, 0,
-        -10, 0, 0, 5, 5, 5, 5, 0, -5, -5, 0, 5, 5, 5, 5, 0, -5, -10, 0, 5, 5, 5, 5, 0, -10, -10, 0,
-        0, 0, 0, 0, 0, -10, -20, -10, -10, -5, -5, -10, -10, -20,
-    ];
-    const BLACK_QUEEN_SQUARE_TABLE: [i64; 64] = [
-        -20, -10, -10, -5, -5, -10, -10, -20, -10, 0, 0, 0, 0, 0, 0, -10, -10, 0, 5, 5, 5, 5, 0,
-        -10, -5, 0, 5, 5, 5, 5, 0, -5, 0, 0, 5, 5, 5, 5, 0, -5, -10, 5, 5, 5, 5, 5, 0, -10, -10, 0,
-        5, 0, 0, 0, 0, -10, -20, -10, -10, -5, -5, -10, -10, -20,
-    ];
-    const WHITE_KING_SQUARE_TABLE: [i64; 64] = [
-        20, 30, 10, 0, 0, 10, 30, 20, 20, 20, 0, 0, 0, 0, 20, 20, -10, -20, -20, -20, -20, -20,
-        -20, -10, -20, -30, -30, -40, -40, -30, -30, -20, -30, -40, -40, -50, -50, -40, -40, -30,
-        -30, -40, -40, -50, -50, -40, -40, -30, -30, -40, -40, -50, -50, -40, -40, -30, -30, -40,
-        -40, -50, -50, -40, -40, -30,
-    ];
-    const BLACK_KING_SQUARE_TABLE: [i64; 64] = [
-        -30, -40, -40, -50, -50, -40, -40, -30, -30, -40, -40, -50, -50, -40, -40, -30, -30, -40,
-        -40, -50, -50, -40, -40, -30, -30, -40, -40, -50, -50, -40, -40, -30, -20, -30, -30, -40,
-        -40, -30, -30, -20, -10, -20, -20, -20, -20, -20, -20, -10, 20, 20, 0, 0, 0, 0, 20, 20, 20,
-        30, 10, 0, 0, 10, 30, 20,
-    ];
-
-    fn material_scores(&self) -> Score {
-        let mut score = 0;
-        for square in 0..64 {
-            score += self.board.get_piece(square).score();
-        }
-        Score::Score(score)
-    }
-
-    fn square_table_scores(&self) -> Score {
-        let mut score = 0;
-        for square in 0..64 {
-            let piece = self.board.get_piece(square);
-            if piece != Piece::NONE {
-                score += match piece {
-                    Piece {
-                        color: Color::White,
-                        kind: PieceKind::Pawn,
-                    } => Self::WHITE_PAWN_SQUARE_TABLE,
-                    Piece {
-                        color: Color::Black,
-                        kind: PieceKind::Pawn,
-                    } => Self::BLACK_PAWN_SQUARE_TABLE,
-                    Piece {
-                        color: Color::White,
-                        kind: PieceKind::Rook,
-                    } => Self::WHITE_ROOK_SQUARE_TABLE,
-                    Piece {
-                        color: Color::Black,
-                        kind: PieceKind::Rook,
-                    } => Self::BLACK_ROOK_SQUARE_TABLE,
-                    Piece {
-                        color: Color::White,
-                        kind: PieceKind::Knight,
-                    } => Self::KNIGHT_SQUARE_TABLE,
-                    Piece {
-                        color: Color::Black,
-                        kind: PieceKind::Knight,
-                    } => Self::KNIGHT_SQUARE_TABLE,
-                    Piece {
-                        color: Color::White,
-                        kind: PieceKind::Bishop,
-                    } => Self::WHITE_BISHOP_SQUARE_TABLE,
-                    Piece {
-                        color: Color::Black,
-                        kind: PieceKind::Bishop,
-                    } => Self::BLACK_BISHOP_SQUARE_TABLE,
-                    Piece {
-                        color: Color::White,
-                        kind: PieceKind::Queen,
-                    } => Self::WHITE_QUEEN_SQUARE_TABLE,
-                    Piece {
-                        color: Color::Black,
-                        kind: PieceKind::Queen,
-                    } => Self::BLACK_QUEEN_SQUARE_TABLE,
-                    Piece {
-                        color: Color::White,
-                        kind: PieceKind::King,
-                    } => Self::WHITE_KING_SQUARE_TABLE,
-                    Piece {
-                        color: Color::Black,
-                        kind: PieceKind::King,
-                    } => Self::BLACK_KING_SQUARE_TABLE,
-                    _ => unreachable!(),
-                }[square as usize]
-                    * match piece.color {
-                        Color::White => 1,
-                        Color::Black => -1,
-                        Color::None => unreachable!(),
-                    };
-            }
-        }
-        Score::Score(score)
-    }
-
-    fn checkmate_stalemate(&mut self) -> Score {
-        let moves = self.board.generate_moves();
-        let score = if moves.is_empty() {
-            if moves.in_check {
-                Score::OppMate(0)
-            } else {
-                Score::Draw(0)
-            }
-        } else {
-            Score::Score(0)
-        };
-        match self.board.turn {
-            Color::White => score,
-            Color::Black => score.flip_score(),
-            Color::None => unreachable!(),
+    fn is_stopped(&self) -> bool {
+        if self.nodes >= self.max_nodes {
+            *self.stopper.write().unwrap() = Status::Stopping;
         }
+        *self.stopper.read().unwrap() == Status::Stopping
     }
 
     fn eval(&mut self) -> Score {
-        let mut score = Score::Score(0);
-        score += self.material_scores();
-        score += self.square_table_scores();
-        score += self.checkmate_stalemate();
+        let score = Score::Score(self.board.material_score + self.board.square_table_score);
         match self.board.turn {
             Color::White => score,
             Color::Black => score.flip_score(),
@@ -578,11 +470,39 @@ impl Search {
         }
     }
 
+    /// Generates and orders the moves on the first call, skipping the already searched hash move
+    fn next_move(
+        &mut self,
+        moves: &mut Option<MoveGeneratorResult>,
+        hash_move: Option<Move>,
+    ) -> Option<Move> {
+        let moves = moves.get_or_insert_with(|| {
+            let mut moves = self.board.generate_moves();
+            moves.sort_by(|a, b| self.mvv_lva(*a, *b));
+            moves
+        });
+        moves.find(|m| Some(*m) != hash_move)
+    }
+
     fn quiescence_search(&mut self, mut alpha: Score, beta: Score) -> Score {
         self.nodes += 1;
-        if *self.stopper.read().unwrap() == Status::Stopping {
+        if self.is_stopped() {
             return Score::Stop;
         }
+        if self.board.draw_reason().is_some() {
+            return Score::Draw(0);
+        }
+        // Mate and stalemate are found here rather than in eval, the quiets are only generated
+        // when there is nothing else to play
+        let mut moves = self.board.generate_captures();
+        if moves.is_empty() && self.board.generate_quiets().is_empty() {
+            return if moves.in_check {
+                Score::OppMate(0)
+            } else {
+                Score::Draw(0)
+            };
+        }
+
         let mut best = self.eval();
         // Stand Pat
         if best >= beta {
@@ -592,7 +512,6 @@ impl Search {
             alpha = best;
         }
 
-        let mut moves = self.board.generate_moves().filter(|e| e.is_capture());
         moves.sort_by(|a, b| self.mvv_lva(*a, *b));
 
         for m in moves {
@@ -617,9 +536,14 @@ impl Search {
 
     fn negamax(&mut self, depth: u8, mut alpha: Score, beta: Score) -> (Score, NodeKind) {
         self.nodes += 1;
-        if *self.stopper.read().unwrap() == Status::Stopping {
+        if self.is_stopped() {
             return (Score::Stop, NodeKind::Stopped);
         }
+        if depth != self.depth
+            && (self.board.is_repetition() || self.board.draw_reason().is_some())
+        {
+            return (Score::Draw(0), NodeKind::Pv);
+        }
         let mut tt_best_move = None;
         if let Some(tt_node) = self.tt.get(&self.board.zobrist_hash) {
             if tt_node.depth >= depth {
@@ -646,19 +570,14 @@ impl Search {
             return (self.quiescence_search(alpha, beta), NodeKind::Pv);
         }
         let (mut best_score, mut best_move) = (Score::OppMate(0), Move::NULL);
-        let mut moves = self.board.generate_moves();
-        let in_check = moves.in_check;
-        moves.sort_by(|a, b| {
-            if let Some(best_move) = tt_best_move {
-                if *a == best_move {
-                    return std::cmp::Ordering::Less;
-                } else if *b == best_move {
-                    return std::cmp::Ordering::Greater;
-                }
-            }
-            self.mvv_lva(*a, *b)
-        });
-        for m in moves {
+        // The hash move is tried before generating anything, it could come from a collision so
+        // it has to be checked first
+        let mut hash_move = tt_best_move.filter(|m| self.board.is_legal(*m));
+        let mut moves = None;
+        while let Some(m) = hash_move
+            .take()
+            .or_else(|| self.next_move(&mut moves, tt_best_move))
+        {
             self.board.make_move(m);
             let score = -self.negamax(depth - 1, -beta, -alpha).0.inc();
             self.board.unmake_move(m);
@@ -687,7 +606,7 @@ impl Search {
         }
 
         if best_move == Move::NULL {
-            if in_check {
+            if moves.is_some_and(|moves| moves.in_check) {
                 best_score = Score::OppMate(0);
             } else {
                 best_score = Score::Draw(0);
@@ -1,17 +1,20 @@
 use crate::{
     board::Board,
+    epd::Epd,
     perft::Position,
+    r#move::Move,
     search::{Score, Search},
     uci::Status,
 };
 
-use vampirc_uci::{UciFen, UciSearchControl};
+use vampirc_uci::{Duration, UciFen, UciSearchControl, UciTimeControl};
 
 use std::{
     fs,
     io::{BufRead, BufReader, Write},
     process::{Child, Command, Stdio},
     sync::{Arc, RwLock},
+    time::Instant,
 };
 
 pub fn search_test(depth: u8, fen: Option<String>) {
@@ -20,7 +23,16 @@ pub fn search_test(depth: u8, fen: Option<String>) {
     let mut stockfish = setup_stockfish();
 
     if let Some(fen) = fen {
-        board.load_position(Some(UciFen(fen.clone())), Vec::new());
+        if let Err(err) = board.load_position(Some(UciFen(fen.clone())), Vec::new()) {
+            eprintln!("Invalid fen: {err}");
+            quit_stockfish(&mut stockfish);
+            return;
+        }
+        if let Err(err) = board.validate() {
+            eprintln!("Illegal position: {err}");
+            quit_stockfish(&mut stockfish);
+            return;
+        }
         board.print();
         board.search_test(depth);
         stockfish_search_test(depth, &fen, &mut stockfish);
@@ -32,7 +44,13 @@ pub fn search_test(depth: u8, fen: Option<String>) {
     let positions: Vec<Position> = serde_json::from_str(&data).unwrap();
 
     for p in &positions {
-        board.load_position(Some(UciFen(p.fen.clone())), Vec::new());
+        board
+            .load_position(Some(UciFen(p.fen.clone())), Vec::new())
+            .expect("Invalid fen in dataset");
+        if let Err(err) = board.validate() {
+            eprintln!("Skipping {}: {err}", p.fen);
+            continue;
+        }
         println!("fen: {}", &p.fen);
         match board.search_test(depth).score {
             Score::OwnMate(_) | Score::OppMate(_) => {
@@ -45,6 +63,114 @@ pub fn search_test(depth: u8, fen: Option<String>) {
     quit_stockfish(&mut stockfish);
 }
 
+/// Checks that every position up to `max_depth` plies deep evaluates the same as its mirror
+pub fn symmetry_test(max_depth: u8, fen: Option<String>) {
+    let mut board = Board::new();
+
+    if let Some(fen) = fen {
+        if let Err(err) = board.load_position(Some(UciFen(fen.clone())), Vec::new()) {
+            eprintln!("Invalid fen: {err}");
+            return;
+        }
+        if let Err(err) = board.validate() {
+            eprintln!("Illegal position: {err}");
+            return;
+        }
+        board.symmetry_test(max_depth);
+        println!("Test successful!");
+        return;
+    }
+
+    let data = fs::read_to_string("./chess-position-generator/perft_dataset.json").unwrap();
+    let positions: Vec<Position> = serde_json::from_str(&data).unwrap();
+
+    for p in &positions {
+        board
+            .load_position(Some(UciFen(p.fen.clone())), Vec::new())
+            .expect("Invalid fen in dataset");
+        if let Err(err) = board.validate() {
+            eprintln!("Skipping {}: {err}", p.fen);
+            continue;
+        }
+        board.symmetry_test(max_depth);
+    }
+    println!("Test successful!");
+}
+
+/// Runs a test suite like WAC or STS, a position passes when the search plays one of its `bm`
+/// moves and none of its `am` moves
+pub fn epd_test(path: &str, depth: Option<u8>, nodes: Option<u64>, move_time: Option<u64>) {
+    let data = match fs::read_to_string(path) {
+        Ok(data) => data,
+        Err(err) => {
+            eprintln!("Failed to read {path}: {err}");
+            return;
+        }
+    };
+
+    let (mut passed, mut failed) = (0, 0);
+    let start = Instant::now();
+
+    for line in data.lines().filter(|line| !line.trim().is_empty()) {
+        let epd = match Epd::parse(line) {
+            Ok(epd) => epd,
+            Err(err) => {
+                eprintln!("Skipping {line}: {err}");
+                continue;
+            }
+        };
+        if epd.best_moves.is_empty() && epd.avoid_moves.is_empty() {
+            eprintln!("Skipping {line}: no bm or am");
+            continue;
+        }
+
+        let position_start = Instant::now();
+        let pv = Search::go(
+            epd.board.clone(),
+            Some(UciSearchControl {
+                search_moves: Vec::new(),
+                mate: None,
+                depth,
+                nodes,
+            }),
+            move_time.map(|move_time| {
+                UciTimeControl::MoveTime(Duration::milliseconds(move_time as i64))
+            }),
+            Arc::new(RwLock::new(Status::Go)),
+        )
+        .pv;
+        let elapsed = position_start.elapsed();
+
+        let pass = pv != Move::NULL
+            && (epd.best_moves.is_empty() || epd.best_moves.contains(&pv))
+            && !epd.avoid_moves.contains(&pv);
+        if pass {
+            passed += 1;
+        } else {
+            failed += 1;
+        }
+
+        let mut board = epd.board.clone();
+        println!(
+            "{} {}: {} in {:.3}s",
+            epd.id.as_deref().unwrap_or(&epd.board.to_fen()),
+            if pass { "passed" } else { "failed" },
+            if pv == Move::NULL {
+                String::from("no move")
+            } else {
+                board.move_to_san(pv)
+            },
+            elapsed.as_secs_f64(),
+        );
+    }
+
+    println!(
+        "Passed {passed}/{} in {:.3}s",
+        passed + failed,
+        start.elapsed().as_secs_f64()
+    );
+}
+
 fn read_line(stockfish: &mut Child) -> String {
     let stdout = stockfish.stdout.as_mut().expect("Failed to get stdout");
 
@@ -127,6 +253,31 @@ fn quit_stockfish(stockfish: &mut Child) {
 }
 
 impl Board {
+    fn symmetry_test(&mut self, depth: u8) {
+        let mirror = self.mirror();
+        let (eval, mirror_eval) = (
+            Search::static_eval(self.clone()),
+            Search::static_eval(mirror.clone()),
+        );
+        if eval != mirror_eval {
+            eprintln!("Evaluation not symmetric: {eval} != {mirror_eval}");
+            eprintln!("Position: {}", self.to_fen());
+            eprintln!("Mirrored: {}", mirror.to_fen());
+            self.print();
+            mirror.print();
+            panic!();
+        }
+
+        if depth == 0 {
+            return;
+        }
+        for m in self.generate_moves() {
+            self.make_move(m);
+            self.symmetry_test(depth - 1);
+            self.unmake_move(m);
+        }
+    }
+
     fn search_test(&'_ mut self, depth: u8) -> Search {
         println!("Me");
         Search::go(
@@ -1,10 +1,14 @@
-use vampirc_uci::parse_with_unknown;
-use vampirc_uci::{MessageList, Serializable, UciMessage};
+use vampirc_uci::{parse_one, parse_with_unknown};
+use vampirc_uci::{
+    MessageList, Serializable, UciFen, UciMessage, UciMove, UciOptionConfig, UciTimeControl,
+};
 
 use std::sync::{Arc, RwLock};
 use std::thread;
 
 use crate::board::Board;
+use crate::book::Book;
+use crate::r#move::Move;
 use crate::search::Search;
 
 #[derive(Debug, PartialEq)]
@@ -17,6 +21,8 @@ pub enum Status {
 pub fn run() {
     let stopper = Arc::new(RwLock::new(Status::Idle));
     let mut board = Board::new();
+    let mut book: Option<Book> = None;
+    let mut book_best_move = false;
     loop {
         let mut input = String::new();
         std::io::stdin()
@@ -34,6 +40,30 @@ pub fn run() {
                         }
                         .serialize()
                     );
+                    println!(
+                        "{}",
+                        UciMessage::Option(UciOptionConfig::String {
+                            name: String::from("Book"),
+                            default: Some(String::new()),
+                        })
+                        .serialize()
+                    );
+                    println!(
+                        "{}",
+                        UciMessage::Option(UciOptionConfig::Check {
+                            name: String::from("BookBestMove"),
+                            default: Some(false),
+                        })
+                        .serialize()
+                    );
+                    println!(
+                        "{}",
+                        UciMessage::Option(UciOptionConfig::Check {
+                            name: String::from("UCI_Chess960"),
+                            default: Some(false),
+                        })
+                        .serialize()
+                    );
                     println!("{}", UciMessage::UciOk.serialize());
                 }
 
@@ -44,8 +74,30 @@ pub fn run() {
                     search_control,
                     ..
                 } => {
+                    // The GUI expects no bestmove before it sends stop in an infinite or ponder
+                    // search
+                    let until_stop = matches!(
+                        time_control,
+                        Some(UciTimeControl::Infinite | UciTimeControl::Ponder)
+                    );
+                    if let Some(book_move) = book
+                        .as_ref()
+                        .filter(|_| !until_stop)
+                        .and_then(|book| book.probe(&mut board, book_best_move))
+                    {
+                        println!(
+                            "{}",
+                            UciMessage::BestMove {
+                                best_move: book_move.as_ucimove(board.chess960),
+                                ponder: None,
+                            }
+                        );
+                        continue;
+                    }
+
                     *stopper.write().expect("Failed to start the search") = Status::Go;
                     let board = board.clone();
+                    let chess960 = board.chess960;
                     let stopper = stopper.clone();
                     thread::spawn(move || {
                         println!(
@@ -58,7 +110,7 @@ pub fn run() {
                                     stopper.clone()
                                 )
                                 .pv
-                                .as_ucimove(),
+                                .as_ucimove(chess960),
                                 ponder: None,
                             }
                         );
@@ -66,8 +118,33 @@ pub fn run() {
                     });
                 }
 
+                UciMessage::SetOption { name, value } => match name.as_str() {
+                    "Book" => {
+                        book = None;
+                        if let Some(path) = value.filter(|path| !path.is_empty()) {
+                            match Book::open(&path) {
+                                Ok(opened) => book = Some(opened),
+                                Err(err) => println!(
+                                    "{}",
+                                    UciMessage::info_string(format!(
+                                        "Failed to open book {path}: {err}"
+                                    ))
+                                    .serialize()
+                                ),
+                            }
+                        }
+                    }
+                    "BookBestMove" => book_best_move = value.as_deref() == Some("true"),
+                    "UCI_Chess960" => board.chess960 = value.as_deref() == Some("true"),
+                    _ => eprintln!("Unknown option: {name}"),
+                },
+
                 UciMessage::UciNewGame => board.new_game(),
-                UciMessage::Position { fen, moves, .. } => board.load_position(fen, moves),
+                UciMessage::Position { fen, moves, .. } => set_position(&mut board, fen, moves),
+                UciMessage::Unknown(message, _) => match parse_shredder_position(&message) {
+                    Some((fen, moves)) => set_position(&mut board, Some(fen), moves),
+                    None => eprintln!("Command not implemented: {message}"),
+                },
 
                 UciMessage::Stop => {
                     *stopper.write().expect("Failed to stop the search") = Status::Stopping
@@ -80,3 +157,44 @@ pub fn run() {
         }
     }
 }
+
+fn set_position(board: &mut Board, fen: Option<UciFen>, moves: Vec<UciMove>) {
+    let mut position = Board::new();
+    position.chess960 = board.chess960;
+    let result = match position.load_position(fen, Vec::new()) {
+        Ok(()) => position
+            .validate()
+            .map_err(|err| format!("Illegal position: {err}")),
+        Err(err) => Err(format!("Invalid fen: {err}")),
+    }
+    .and_then(|()| play_moves(&mut position, moves));
+    match result {
+        Ok(()) => *board = position,
+        Err(err) => println!("{}", UciMessage::info_string(err).serialize()),
+    }
+}
+
+/// Every move from the GUI is checked before it is made, a bad one would corrupt the board
+fn play_moves(board: &mut Board, moves: Vec<UciMove>) -> Result<(), String> {
+    for ucimove in moves {
+        let m = Move::from_ucimove(board, ucimove);
+        if !board.is_legal(m) {
+            return Err(format!("Illegal move: {ucimove}"));
+        }
+        board.make_move(m);
+    }
+    Ok(())
+}
+
+/// vampirc only accepts KQkq castling rights, so Shredder-FEN positions are split up by hand
+fn parse_shredder_position(message: &str) -> Option<(UciFen, Vec<UciMove>)> {
+    let position = message.trim().strip_prefix("position fen ")?;
+    let (fen, moves) = match position.split_once(" moves ") {
+        Some((fen, moves)) => match parse_one(&format!("position startpos moves {moves}")) {
+            UciMessage::Position { moves, .. } => (fen, moves),
+            _ => return None,
+        },
+        None => (position, Vec::new()),
+    };
+    Some((UciFen(fen.trim().to_string()), moves))
+}
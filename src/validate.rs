@@ -0,0 +1,124 @@
+use crate::board::{AsSquare, Bitboard, Board, Color, PieceKind, Square};
+
+const BACK_RANKS: Bitboard = 0xff000000000000ff;
+
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum PositionError {
+    MissingKing(Color),
+    TooManyKings(Color),
+    OpponentInCheck,
+    PawnOnBackRank(Square),
+    InvalidCastlingRight(char),
+    InvalidEnPassant(Square),
+}
+
+fn color_name(color: Color) -> &'static str {
+    match color {
+        Color::White => "white",
+        Color::Black => "black",
+        Color::None => unreachable!(),
+    }
+}
+
+impl std::fmt::Display for PositionError {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        match self {
+            PositionError::MissingKing(color) => write!(f, "{} has no king", color_name(*color)),
+            PositionError::TooManyKings(color) => {
+                write!(f, "{} has more than one king", color_name(*color))
+            }
+            PositionError::OpponentInCheck => write!(f, "the side not to move is in check"),
+            PositionError::PawnOnBackRank(square) => {
+                write!(f, "pawn on back rank at {}", square.as_square())
+            }
+            PositionError::InvalidCastlingRight(right) => write!(
+                f,
+                "castling right '{right}' without its king and rook on the back rank"
+            ),
+            PositionError::InvalidEnPassant(square) => write!(
+                f,
+                "en passant square {} without a pawn that just double-pushed",
+                square.as_square()
+            ),
+        }
+    }
+}
+
+impl std::error::Error for PositionError {}
+
+impl Board {
+    /// Checks the invariants the move generator relies on
+    pub fn validate(&self) -> Result<(), PositionError> {
+        for (color, pieces) in [
+            (Color::White, self.white_pieces),
+            (Color::Black, self.black_pieces),
+        ] {
+            match (self.kings & pieces).count_ones() {
+                0 => return Err(PositionError::MissingKing(color)),
+                1 => (),
+                _ => return Err(PositionError::TooManyKings(color)),
+            }
+        }
+
+        let opponent_king = (self.kings & self.opponent_pieces()).trailing_zeros() as Square;
+        let occupied = self.white_pieces | self.black_pieces;
+        if self.attackers(opponent_king, self.turn, occupied) > 0 {
+            return Err(PositionError::OpponentInCheck);
+        }
+
+        let back_rank_pawns = self.pawns & BACK_RANKS;
+        if back_rank_pawns > 0 {
+            return Err(PositionError::PawnOnBackRank(
+                back_rank_pawns.trailing_zeros() as Square,
+            ));
+        }
+
+        for (i, (right, color, back_rank)) in [
+            ('K', Color::White, 0),
+            ('Q', Color::White, 0),
+            ('k', Color::Black, 7),
+            ('q', Color::Black, 7),
+        ]
+        .into_iter()
+        .enumerate()
+        {
+            if self.castling_rights & (0b1000 >> i) == 0 {
+                continue;
+            }
+            let pieces = match color {
+                Color::White => self.white_pieces,
+                _ => self.black_pieces,
+            };
+            let king = (self.kings & pieces).trailing_zeros() as Square;
+            let rook = self.castling_rooks[i];
+            let rook_piece = self.get_piece(rook);
+            // The king side rook has to be on the h side of the king
+            if king / 8 != back_rank
+                || rook / 8 != back_rank
+                || rook_piece.kind != PieceKind::Rook
+                || rook_piece.color != color
+                || (rook > king) != (i % 2 == 0)
+            {
+                return Err(PositionError::InvalidCastlingRight(right));
+            }
+        }
+
+        if self.ep != -1 {
+            let (ep_rank, opponent) = match self.turn {
+                Color::White => (5, Color::Black),
+                Color::Black => (2, Color::White),
+                Color::None => unreachable!(),
+            };
+            let pawn = self.get_piece(self.ep - self.turn);
+            if self.ep / 8 != ep_rank
+                || occupied & (1 << self.ep | 1 << (self.ep + self.turn)) > 0
+                || pawn.kind != PieceKind::Pawn
+                || pawn.color != opponent
+            {
+                return Err(PositionError::InvalidEnPassant(self.ep));
+            }
+        }
+
+        Ok(())
+    }
+}